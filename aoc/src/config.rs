@@ -0,0 +1,113 @@
+use serde::de::DeserializeOwned;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use toml::value::Table;
+use toml::Value;
+
+pub const CONFIG_FILE: &str = "aoc.toml";
+pub const CONFIG_ENV: &str = "AOC_CONFIG";
+
+/// Settings for one day, read from the workspace `aoc.toml`.
+///
+/// Keys in a `[dayN]` table override the same key in `[defaults]`. Command
+/// line flags are expected to take precedence over both, so every getter is
+/// only consulted once a flag was left unset.
+#[derive(Debug, Default)]
+pub struct Config {
+    day: u8,
+    root: PathBuf,
+    defaults: Table,
+    overrides: Table,
+}
+
+// Looks for the config in `$AOC_CONFIG` first, then in the working directory
+// and each of its parents.
+fn find_config() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(CONFIG_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    let cwd = env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+fn take_table(table: &mut Table, key: &str) -> Result<Table, String> {
+    match table.remove(key) {
+        Some(Value::Table(t)) => Ok(t),
+        Some(_) => Err(format!("[{}] must be a table", key)),
+        None => Ok(Table::new()),
+    }
+}
+
+impl Config {
+    /// Loads the settings for `day`, exiting with a message if the config
+    /// file exists but cannot be used.
+    pub fn for_day(day: u8) -> Config {
+        match find_config() {
+            Some(path) => Config::load(&path, day).unwrap_or_else(|e| {
+                eprintln!("{}: {}", path.display(), e);
+                process::exit(1);
+            }),
+            None => Config {
+                day,
+                ..Config::default()
+            },
+        }
+    }
+
+    pub fn load(path: &Path, day: u8) -> Result<Config, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Config::parse(&text, day, path.parent().unwrap_or_else(|| Path::new("")))
+    }
+
+    pub fn parse(text: &str, day: u8, root: &Path) -> Result<Config, String> {
+        let mut table: Table = toml::from_str(text).map_err(|e| e.to_string())?;
+        Ok(Config {
+            day,
+            root: root.to_path_buf(),
+            defaults: take_table(&mut table, "defaults")?,
+            overrides: take_table(&mut table, &format!("day{}", day))?,
+        })
+    }
+
+    pub fn day(&self) -> u8 {
+        self.day
+    }
+
+    /// Looks up `key`, exiting with a message if it has the wrong type.
+    pub fn get<T>(&self, key: &str) -> Option<T>
+    where
+        T: DeserializeOwned,
+    {
+        let (section, value) = match self.overrides.get(key) {
+            Some(value) => (format!("day{}", self.day), value),
+            None => ("defaults".to_string(), self.defaults.get(key)?),
+        };
+        match value.clone().try_into() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                eprintln!("Invalid {}.{} in {}: {}", section, key, CONFIG_FILE, e);
+                process::exit(1);
+            }
+        }
+    }
+
+    /// Looks up a path, expanding `{day}` and resolving it against the
+    /// directory holding the config file.
+    pub fn path(&self, key: &str) -> Option<PathBuf> {
+        self.get::<String>(key)
+            .map(|p| self.root.join(p.replace("{day}", &self.day.to_string())))
+    }
+
+    /// Picks the input file given on the command line, falling back to the
+    /// configured `input`.
+    pub fn input(&self, cli: Option<PathBuf>) -> PathBuf {
+        cli.or_else(|| self.path("input")).unwrap_or_else(|| {
+            eprintln!("No input file given and no `input` set in {}", CONFIG_FILE);
+            process::exit(1);
+        })
+    }
+}
@@ -0,0 +1,246 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead};
+use std::path::Path;
+use structopt::StructOpt;
+
+// Command line options shared by every day for cleaning up input files.
+#[derive(Debug, Default, StructOpt)]
+pub struct NormalizeOpts {
+    /// Report every fix made while normalising the input
+    #[structopt(long)]
+    pub strict: bool,
+}
+
+impl NormalizeOpts {
+    /// Normalises the lines of `reader`, carriage returns included.
+    pub fn read<R: BufRead>(&self, reader: R, blanks: Blanks) -> Normalize<Lines<R>> {
+        self.apply(Lines { reader }, blanks)
+    }
+
+    pub fn apply<I>(&self, lines: I, blanks: Blanks) -> Normalize<I>
+    where
+        I: Iterator<Item = io::Result<String>>,
+    {
+        Normalize {
+            lines,
+            blanks,
+            strict: self.strict,
+            line_no: 0,
+            started: false,
+            pending_blank: None,
+            queued: None,
+            finished: false,
+            report: Report::default(),
+        }
+    }
+}
+
+/// Opens the input as a buffered reader, treating `-` as stdin.
+pub fn open_input<P>(filename: P) -> io::Result<Box<dyn BufRead>>
+where
+    P: AsRef<Path>,
+{
+    if filename.as_ref() == Path::new("-") {
+        Ok(Box::new(io::stdin().lock()))
+    } else {
+        let file = File::open(filename)?;
+        Ok(Box::new(io::BufReader::new(file)))
+    }
+}
+
+/// The lines of a reader with only the `\n` removed. Unlike
+/// `BufRead::lines` it leaves the `\r` of a CRLF line ending in place, so
+/// `Normalize` can strip it and report it.
+#[derive(Debug)]
+pub struct Lines<R> {
+    reader: R,
+}
+
+impl<R: BufRead> Iterator for Lines<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.ends_with('\n') {
+                    line.pop();
+                }
+                Some(Ok(line))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// How blank lines are treated once the other fixes have been applied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Blanks {
+    /// The input has no sections, so every blank line is dropped
+    Drop,
+    /// Blank lines separate sections, so runs of them become a single blank
+    /// line and leading or trailing ones are dropped
+    Collapse,
+}
+
+/// How often one kind of fix was made, and the first line it was made on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Fix {
+    pub count: usize,
+    pub first_line: usize,
+}
+
+impl Fix {
+    fn add(&mut self, line_no: usize) {
+        if self.count == 0 {
+            self.first_line = line_no;
+        }
+        self.count += 1;
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub byte_order_mark: bool,
+    pub carriage_returns: Fix,
+    pub trailing_whitespace: Fix,
+    pub blank_lines: Fix,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        *self == Report::default()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Normalised input:")?;
+        if self.byte_order_mark {
+            writeln!(f, "  removed byte order mark")?;
+        }
+        for (fix, prefix, suffix) in [
+            (self.carriage_returns, "stripped carriage return from", ""),
+            (
+                self.trailing_whitespace,
+                "trimmed trailing whitespace on",
+                "",
+            ),
+            (self.blank_lines, "removed", " blank"),
+        ] {
+            if fix.count > 0 {
+                writeln!(
+                    f,
+                    "  {} {}{} line{} (first on line {})",
+                    prefix,
+                    fix.count,
+                    suffix,
+                    if fix.count == 1 { "" } else { "s" },
+                    fix.first_line
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lazily strips BOMs, `\r` and trailing whitespace from each line and tidies
+/// up blank lines, so it can sit in front of a streaming solver.
+///
+/// In strict mode the fixes are written to stderr once the input runs out.
+#[derive(Debug)]
+pub struct Normalize<I> {
+    lines: I,
+    blanks: Blanks,
+    strict: bool,
+    line_no: usize,
+    started: bool,
+    pending_blank: Option<usize>,
+    queued: Option<String>,
+    finished: bool,
+    report: Report,
+}
+
+impl<I> Normalize<I> {
+    pub fn report(&self) -> &Report {
+        &self.report
+    }
+
+    /// The number of the last line read from the underlying input. With
+    /// `Blanks::Drop` this is the line that was just returned.
+    pub fn line_no(&self) -> usize {
+        self.line_no
+    }
+
+    fn clean(&mut self, mut line: String) -> String {
+        if self.line_no == 1 && line.starts_with('\u{feff}') {
+            line.remove(0);
+            self.report.byte_order_mark = true;
+        }
+        if line.ends_with('\r') {
+            line.pop();
+            self.report.carriage_returns.add(self.line_no);
+        }
+        let trimmed = line.trim_end().len();
+        if trimmed < line.len() {
+            line.truncate(trimmed);
+            self.report.trailing_whitespace.add(self.line_no);
+        }
+        line
+    }
+
+    fn finish(&mut self) {
+        if let Some(line_no) = self.pending_blank.take() {
+            self.report.blank_lines.add(line_no);
+        }
+        if self.strict && !self.report.is_clean() {
+            eprint!("{}", self.report);
+        }
+    }
+}
+
+impl<I> Iterator for Normalize<I>
+where
+    I: Iterator<Item = io::Result<String>>,
+{
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(line) = self.queued.take() {
+            return Some(Ok(line));
+        }
+        loop {
+            let line = match self.lines.next() {
+                Some(Ok(line)) => line,
+                Some(Err(e)) => return Some(Err(e)),
+                None => {
+                    if !self.finished {
+                        self.finished = true;
+                        self.finish();
+                    }
+                    return None;
+                }
+            };
+            self.line_no += 1;
+            let line = self.clean(line);
+            if line.is_empty() {
+                let keep =
+                    self.blanks == Blanks::Collapse && self.started && self.pending_blank.is_none();
+                if keep {
+                    self.pending_blank = Some(self.line_no);
+                } else {
+                    self.report.blank_lines.add(self.line_no);
+                }
+                continue;
+            }
+            self.started = true;
+            if self.pending_blank.take().is_some() {
+                self.queued = Some(line);
+                return Some(Ok(String::new()));
+            }
+            return Some(Ok(line));
+        }
+    }
+}
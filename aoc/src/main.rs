@@ -0,0 +1,89 @@
+use aoc::perf::{self, Verdict};
+use std::path::PathBuf;
+use std::process;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "aoc", about = "Advent of Code workspace tools")]
+enum Cli {
+    /// Inspect recorded part timings
+    Perf(PerfCommand),
+}
+
+#[derive(Debug, StructOpt)]
+enum PerfCommand {
+    /// Show speedups and regressions between two revisions. `REV-dirty`
+    /// names the runs made with uncommitted changes on top of `REV`
+    Compare {
+        /// Baseline revision
+        rev_a: String,
+        /// Revision to compare against the baseline
+        rev_b: String,
+        /// History file written by `--perf-history`
+        #[structopt(long, parse(from_os_str), default_value = "perf-history.tsv")]
+        history: PathBuf,
+        /// Changes smaller than this percentage are reported as noise
+        #[structopt(long, default_value = "5")]
+        threshold: f64,
+    },
+}
+
+// A `-dirty` suffix names the runs made with uncommitted changes on top of a
+// revision.
+fn resolve_or_exit(rev: &str) -> String {
+    let (base, dirty) = match rev.strip_suffix("-dirty") {
+        Some(base) => (base, "-dirty"),
+        None => (rev, ""),
+    };
+    let hash = perf::resolve_rev(base).unwrap_or_else(|| {
+        eprintln!("Unknown revision: {}", rev);
+        process::exit(1);
+    });
+    format!("{}{}", hash, dirty)
+}
+
+fn main() {
+    match Cli::from_args() {
+        Cli::Perf(PerfCommand::Compare {
+            rev_a,
+            rev_b,
+            history,
+            threshold,
+        }) => {
+            let a = resolve_or_exit(&rev_a);
+            let b = resolve_or_exit(&rev_b);
+            let samples = perf::load(&history).unwrap_or_else(|e| {
+                eprintln!("Unable to read {}: {}", history.display(), e);
+                process::exit(1);
+            });
+            let comparisons = perf::compare(&samples, &a, &b, threshold / 100.0);
+            if comparisons.is_empty() {
+                println!(
+                    "No day has timings recorded for both {} and {}",
+                    rev_a, rev_b
+                );
+                return;
+            }
+            println!(
+                "{:<8}{:>14}{:>14}{:>10}{:>9}  Verdict",
+                "Part", &rev_a, &rev_b, "Speedup", "Noise"
+            );
+            for c in comparisons {
+                let verdict = match c.verdict {
+                    Verdict::Faster => "faster",
+                    Verdict::Slower => "REGRESSION",
+                    Verdict::Unchanged => "~",
+                };
+                println!(
+                    "{:<8}{:>14}{:>14}{:>9.2}x{:>8.1}%  {}",
+                    format!("{}.{}", c.day, c.part),
+                    format!("{:.2?} ({})", c.median.0, c.runs.0),
+                    format!("{:.2?} ({})", c.median.1, c.runs.1),
+                    c.speedup,
+                    c.noise * 100.0,
+                    verdict
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use crate::config::Config;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+use structopt::StructOpt;
+
+// Command line options shared by every day for recording part timings.
+#[derive(Debug, Default, StructOpt)]
+pub struct PerfOpts {
+    /// Append the time taken by each part to this history file
+    #[structopt(long, parse(from_os_str))]
+    pub perf_history: Option<PathBuf>,
+}
+
+impl PerfOpts {
+    /// Falls back to the `perf_history` config setting when the flag is unset.
+    pub fn recorder(&self, config: &Config) -> Recorder {
+        let history = self
+            .perf_history
+            .clone()
+            .or_else(|| config.path("perf_history"));
+        Recorder {
+            day: config.day(),
+            commit: history.as_ref().and_then(|_| current_commit()),
+            history,
+        }
+    }
+}
+
+/// Times the parts of a single day and appends them to the history file.
+#[derive(Debug)]
+pub struct Recorder {
+    day: u8,
+    history: Option<PathBuf>,
+    commit: Option<String>,
+}
+
+impl Recorder {
+    pub fn part<T, F>(&self, part: u8, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        let start = Instant::now();
+        let result = f();
+        let elapsed = start.elapsed();
+        if let (Some(history), Some(commit)) = (&self.history, &self.commit) {
+            let sample = Sample {
+                commit: commit.clone(),
+                day: self.day,
+                part,
+                elapsed,
+            };
+            if let Err(e) = append(history, &sample) {
+                eprintln!("Unable to record timing in {}: {}", history.display(), e);
+            }
+        }
+        result
+    }
+}
+
+/// One timed run of a part, as stored in the history file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    pub commit: String,
+    pub day: u8,
+    pub part: u8,
+    pub elapsed: Duration,
+}
+
+impl Sample {
+    // Lines are `commit<TAB>day<TAB>part<TAB>nanoseconds`
+    fn parse(line: &str) -> Option<Sample> {
+        let mut fields = line.split('\t');
+        let sample = Sample {
+            commit: fields.next()?.to_string(),
+            day: fields.next()?.parse().ok()?,
+            part: fields.next()?.parse().ok()?,
+            elapsed: Duration::from_nanos(fields.next()?.parse().ok()?),
+        };
+        if fields.next().is_some() {
+            None
+        } else {
+            Some(sample)
+        }
+    }
+}
+
+/// Resolves a git revision to its full commit hash.
+pub fn resolve_rev(rev: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("{}^{{commit}}", rev))
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout)
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+// The commit being timed, as `HASH-dirty` when tracked files have uncommitted
+// changes so those runs are never taken for the clean commit. Untracked files
+// are ignored, as the history file itself is usually one of them.
+fn current_commit() -> Option<String> {
+    let head = resolve_rev("HEAD")?;
+    let output = Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=no"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    if output.stdout.is_empty() {
+        Some(head)
+    } else {
+        Some(format!("{}-dirty", head))
+    }
+}
+
+pub fn append<P>(history: P, sample: &Sample) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    let mut file = OpenOptions::new().create(true).append(true).open(history)?;
+    writeln!(
+        file,
+        "{}\t{}\t{}\t{}",
+        sample.commit,
+        sample.day,
+        sample.part,
+        sample.elapsed.as_nanos()
+    )
+}
+
+/// Reads every well formed sample from the history file.
+pub fn load<P>(history: P) -> io::Result<Vec<Sample>>
+where
+    P: AsRef<Path>,
+{
+    let file = File::open(history)?;
+    let mut samples = Vec::new();
+    for line in io::BufReader::new(file).lines() {
+        if let Some(sample) = Sample::parse(&line?) {
+            samples.push(sample);
+        }
+    }
+    Ok(samples)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Faster,
+    Slower,
+    Unchanged,
+}
+
+/// The change in median run time of one part between two revisions.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub day: u8,
+    pub part: u8,
+    pub runs: (usize, usize),
+    pub median: (Duration, Duration),
+    /// How many times faster `b` is than `a`
+    pub speedup: f64,
+    /// The relative change that has to be exceeded to not count as noise
+    pub noise: f64,
+    pub verdict: Verdict,
+}
+
+fn median(sorted: &[f64]) -> f64 {
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+// Median absolute deviation relative to the median, a spread measure that is
+// not thrown off by the odd slow run.
+fn relative_spread(sorted: &[f64], mid: f64) -> f64 {
+    let mut deviations: Vec<f64> = sorted.iter().map(|t| (t - mid).abs()).collect();
+    deviations.sort_by(f64::total_cmp);
+    if mid > 0.0 {
+        median(&deviations) / mid
+    } else {
+        0.0
+    }
+}
+
+/// Compares every day and part that has samples for both commits.
+///
+/// A change only counts as a speedup or regression when it is larger than
+/// both `threshold` and the run to run spread of either commit.
+pub fn compare(samples: &[Sample], a: &str, b: &str, threshold: f64) -> Vec<Comparison> {
+    let mut runs: BTreeMap<(u8, u8), (Vec<f64>, Vec<f64>)> = BTreeMap::new();
+    for sample in samples {
+        let nanos = sample.elapsed.as_nanos() as f64;
+        let entry = runs.entry((sample.day, sample.part)).or_default();
+        if sample.commit == a {
+            entry.0.push(nanos);
+        }
+        if sample.commit == b {
+            entry.1.push(nanos);
+        }
+    }
+    runs.into_iter()
+        .filter(|(_, (a, b))| !a.is_empty() && !b.is_empty())
+        .map(|((day, part), (mut a, mut b))| {
+            a.sort_by(f64::total_cmp);
+            b.sort_by(f64::total_cmp);
+            let (mid_a, mid_b) = (median(&a), median(&b));
+            let noise = threshold
+                .max(relative_spread(&a, mid_a))
+                .max(relative_spread(&b, mid_b));
+            let change = (mid_b - mid_a) / mid_a;
+            let verdict = if change < -noise {
+                Verdict::Faster
+            } else if change > noise {
+                Verdict::Slower
+            } else {
+                Verdict::Unchanged
+            };
+            Comparison {
+                day,
+                part,
+                runs: (a.len(), b.len()),
+                median: (
+                    Duration::from_nanos(mid_a as u64),
+                    Duration::from_nanos(mid_b as u64),
+                ),
+                speedup: mid_a / mid_b,
+                noise,
+                verdict,
+            }
+        })
+        .collect()
+}
@@ -1,36 +1,522 @@
-use std::fs::File;
+use aoc::config::Config;
+use aoc::input::{open_input, Blanks, Lines, Normalize, NormalizeOpts};
+use aoc::perf::PerfOpts;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
 use std::io::{self, BufRead};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use structopt::clap::{Error, ErrorKind};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Day1", about = "Sonar Sweep")]
 struct Cli {
-    /// Input file
+    /// Input file (or `-` for stdin), defaults to `input` in aoc.toml
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
+
+    /// Solve in a single pass without loading the whole input
+    #[structopt(long)]
+    stream: bool,
+
+    /// Compare sums of this many depths instead of solving both parts
+    #[structopt(long, parse(try_from_str = parse_window))]
+    window: Option<usize>,
+
+    /// Smooth the depths before counting, e.g. `sum:3`, `median:5` or `ema:0.3`
+    #[structopt(long = "filter", number_of_values = 1, conflicts_with = "window")]
+    filters: Vec<Filter>,
+
+    /// Which change to report for `--window` or `--filter` [default: increases]
+    #[structopt(long, possible_values = &["increases", "decreases", "plateaus"])]
+    compare: Option<Comparison>,
+
+    /// Print run, jump and step size statistics instead of solving both parts
+    #[structopt(long, conflicts_with_all = &["window", "filters"])]
+    report: bool,
+
+    #[structopt(flatten)]
+    normalize: NormalizeOpts,
+
+    #[structopt(flatten)]
+    perf: PerfOpts,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Increases,
+    Decreases,
+    Plateaus,
+}
+
+impl Comparison {
+    fn matches<T: PartialOrd>(&self, before: &T, after: &T) -> bool {
+        match self {
+            Comparison::Increases => before < after,
+            Comparison::Decreases => before > after,
+            Comparison::Plateaus => before == after,
+        }
+    }
+}
+
+impl FromStr for Comparison {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "increases" => Ok(Comparison::Increases),
+            "decreases" => Ok(Comparison::Decreases),
+            "plateaus" => Ok(Comparison::Plateaus),
+            _ => Err(format!("unknown comparison: {}", s)),
+        }
+    }
+}
+
+// Compares the sums of each pair of neighbouring windows of `size` depths and
+// returns, for every pair that matches, the index of the depth that enters the
+// later window. The two sums share all but one depth each, so it is enough to
+// compare the depth leaving with the depth entering.
+fn window_events<T: PartialOrd>(data: &[T], size: usize, comparison: Comparison) -> Vec<usize> {
+    if size == 0 {
+        return Vec::new();
+    }
+    data.windows(size + 1)
+        .enumerate()
+        .filter(|(_, w)| comparison.matches(&w[0], &w[size]))
+        .map(|(i, _)| i + size)
+        .collect()
+}
+
+// An inclusive range of reading indices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Run {
+    start: usize,
+    end: usize,
+}
+
+impl Run {
+    fn len(&self) -> usize {
+        self.end - self.start + 1
+    }
+}
+
+const HISTOGRAM_BUCKETS: i64 = 16;
+const HISTOGRAM_WIDTH: usize = 50;
+
+#[derive(Debug)]
+struct DepthReport {
+    readings: usize,
+    skipped: usize,
+    longest_increase: Option<Run>,
+    longest_decrease: Option<Run>,
+    // Index of the reading after the jump, and the signed jump
+    largest_jump: Option<(usize, i64)>,
+    min: u32,
+    max: u32,
+    mean: f64,
+    bucket_width: i64,
+    steps: BTreeMap<i64, usize>,
+}
+
+// Finds the longest stretch of consecutive readings where every step
+// satisfies `step`, keeping the earliest on ties.
+fn longest_run<F>(data: &[u32], step: F) -> Option<Run>
+where
+    F: Fn(u32, u32) -> bool,
+{
+    let mut best: Option<Run> = None;
+    let mut start = 0;
+    for (i, w) in data.windows(2).enumerate() {
+        if !step(w[0], w[1]) {
+            start = i + 1;
+            continue;
+        }
+        let run = Run { start, end: i + 1 };
+        if best.is_none_or(|b| run.len() > b.len()) {
+            best = Some(run);
+        }
+    }
+    best
+}
+
+impl DepthReport {
+    fn new(data: &[u32], skipped: usize) -> Option<DepthReport> {
+        let steps: Vec<i64> = data.windows(2).map(|w| w[1] as i64 - w[0] as i64).collect();
+        let bucket_width = match (steps.iter().min(), steps.iter().max()) {
+            (Some(lo), Some(hi)) => ((hi - lo + 1) + HISTOGRAM_BUCKETS - 1) / HISTOGRAM_BUCKETS,
+            _ => 1,
+        };
+        let mut histogram = BTreeMap::new();
+        for step in &steps {
+            *histogram.entry(step.div_euclid(bucket_width)).or_insert(0) += 1;
+        }
+        Some(DepthReport {
+            readings: data.len(),
+            skipped,
+            longest_increase: longest_run(data, |a, b| a < b),
+            longest_decrease: longest_run(data, |a, b| a > b),
+            largest_jump: steps
+                .iter()
+                .enumerate()
+                .rev()
+                .max_by_key(|(_, step)| step.abs())
+                .map(|(i, step)| (i + 1, *step)),
+            min: *data.iter().min()?,
+            max: *data.iter().max()?,
+            mean: data.iter().map(|d| *d as f64).sum::<f64>() / data.len() as f64,
+            bucket_width,
+            steps: histogram,
+        })
+    }
+
+    fn print(&self) {
+        println!("Readings: {}", self.readings);
+        println!("Skipped lines: {}", self.skipped);
+        println!(
+            "Depth: min {}, max {}, mean {:.2}",
+            self.min, self.max, self.mean
+        );
+        for (name, run) in [
+            ("increasing", self.longest_increase),
+            ("decreasing", self.longest_decrease),
+        ] {
+            match run {
+                Some(r) => println!(
+                    "Longest {} run: {} readings, {} to {}",
+                    name,
+                    r.len(),
+                    r.start,
+                    r.end
+                ),
+                None => println!("Longest {} run: none", name),
+            }
+        }
+        if let Some((i, step)) = self.largest_jump {
+            println!("Largest jump: {:+} into reading {}", step, i);
+        }
+        println!("Step sizes:");
+        let tallest = self.steps.values().max().copied().unwrap_or(0);
+        for (bucket, count) in &self.steps {
+            let lo = bucket * self.bucket_width;
+            let label = if self.bucket_width == 1 {
+                format!("{:+}", lo)
+            } else {
+                format!("{:+}..{:+}", lo, lo + self.bucket_width - 1)
+            };
+            println!(
+                "  {:>9} {:>6} {}",
+                label,
+                count,
+                "#".repeat((count * HISTOGRAM_WIDTH).div_ceil(tallest))
+            );
+        }
+    }
+}
+
+// Depth readings with one column per channel.
+#[derive(Debug, Default)]
+struct Channels {
+    names: Vec<String>,
+    columns: Vec<Vec<u32>>,
+    skipped: usize,
+}
+
+fn split_fields(line: &str) -> Vec<&str> {
+    if line.contains(',') {
+        line.split(',').map(str::trim).collect()
+    } else {
+        line.split_whitespace().collect()
+    }
+}
+
+impl Channels {
+    // Lines may be comma or whitespace separated, and a first line of several
+    // fields without any numbers names the channels. The first line of
+    // readings fixes the channel count, and any line that does not parse to
+    // that many readings is skipped.
+    fn parse<I>(lines: I) -> Channels
+    where
+        I: Iterator<Item = String>,
+    {
+        let mut channels = Channels::default();
+        for (i, line) in lines.enumerate() {
+            let fields = split_fields(&line);
+            let readings: Option<Vec<u32>> = fields.iter().map(|f| f.parse().ok()).collect();
+            match readings {
+                Some(readings) if channels.columns.is_empty() && !readings.is_empty() => {
+                    channels.columns = readings.into_iter().map(|r| vec![r]).collect();
+                }
+                Some(readings) if readings.len() == channels.columns.len() => {
+                    for (column, reading) in channels.columns.iter_mut().zip(readings) {
+                        column.push(reading);
+                    }
+                }
+                None if i == 0
+                    && fields.len() > 1
+                    && fields.iter().all(|f| f.parse::<f64>().is_err()) =>
+                {
+                    channels.names = fields.iter().map(|f| f.to_string()).collect();
+                }
+                _ => channels.skipped += 1,
+            }
+        }
+        if channels.names.len() != channels.columns.len() {
+            channels.names = (1..=channels.columns.len())
+                .map(|c| format!("channel {}", c))
+                .collect();
+        }
+        channels
+    }
+
+    fn derived<F>(&self, combine: F) -> Vec<f64>
+    where
+        F: Fn(&mut dyn Iterator<Item = f64>) -> f64,
+    {
+        let rows = self.columns.first().map_or(0, Vec::len);
+        (0..rows)
+            .map(|row| combine(&mut self.columns.iter().map(|c| c[row] as f64)))
+            .collect()
+    }
+
+    // Every raw channel, followed by the mean, min and max across channels if
+    // there is more than one.
+    fn all(&self) -> Vec<(String, Vec<f64>)> {
+        let count = self.columns.len() as f64;
+        let mut all: Vec<(String, Vec<f64>)> = self
+            .names
+            .iter()
+            .cloned()
+            .zip(
+                self.columns
+                    .iter()
+                    .map(|c| c.iter().map(|d| *d as f64).collect()),
+            )
+            .collect();
+        if self.columns.len() == 1 {
+            return all;
+        }
+        all.push((
+            "mean".to_string(),
+            self.derived(|it| it.sum::<f64>() / count),
+        ));
+        all.push((
+            "min".to_string(),
+            self.derived(|it| it.fold(f64::MAX, f64::min)),
+        ));
+        all.push((
+            "max".to_string(),
+            self.derived(|it| it.fold(f64::MIN, f64::max)),
+        ));
+        all
+    }
+}
+
+// A smoothing filter applied to the depths before changes are counted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Filter {
+    Sum(usize),
+    Median(usize),
+    Ema(f64),
+}
+
+fn parse_window(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(size) if size > 0 => Ok(size),
+        _ => Err("window needs a size of at least 1".to_string()),
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, arg) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected `kind:argument`, got {}", s))?;
+        let size = || match arg.parse::<usize>() {
+            Ok(0) | Err(_) => Err(format!("{} needs a window size of at least 1", kind)),
+            Ok(size) => Ok(size),
+        };
+        match kind {
+            "sum" => Ok(Filter::Sum(size()?)),
+            "median" => Ok(Filter::Median(size()?)),
+            "ema" => match arg.parse::<f64>() {
+                Ok(alpha) if alpha > 0.0 && alpha <= 1.0 => Ok(Filter::Ema(alpha)),
+                _ => Err("ema needs an alpha in (0, 1]".to_string()),
+            },
+            _ => Err(format!("unknown filter: {}", kind)),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Filter::Sum(size) => write!(f, "sum {}", size),
+            Filter::Median(size) => write!(f, "median {}", size),
+            Filter::Ema(alpha) => write!(f, "ema {}", alpha),
+        }
+    }
+}
+
+fn median(window: &[f64]) -> f64 {
+    let mut sorted = window.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+impl Filter {
+    // Counts the changes between neighbouring values of the smoothed series.
+    // A moving sum is compared through `window_events` directly, so that the
+    // original depths are compared and not sums that may have been rounded.
+    fn count(&self, data: &[f64], comparison: Comparison) -> usize {
+        match self {
+            Filter::Sum(size) => window_events(data, *size, comparison).len(),
+            Filter::Median(size) => {
+                let smoothed: Vec<f64> = data.windows(*size).map(median).collect();
+                window_events(&smoothed, 1, comparison).len()
+            }
+            Filter::Ema(alpha) => {
+                let smoothed: Vec<f64> = data
+                    .iter()
+                    .scan(None, |ema: &mut Option<f64>, d| {
+                        let next = ema.map_or(*d, |prev| alpha * d + (1.0 - alpha) * prev);
+                        *ema = Some(next);
+                        Some(next)
+                    })
+                    .collect();
+                window_events(&smoothed, 1, comparison).len()
+            }
+        }
+    }
 }
 
 // The output is wrapped in a Result to allow matching on errors
-// Returns an Iterator to the Reader of the lines of the file.
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+// Returns an Iterator to the normalised lines of the file.
+fn read_lines<P>(
+    filename: P,
+    normalize: &NormalizeOpts,
+) -> io::Result<Normalize<Lines<Box<dyn BufRead>>>>
 where
     P: AsRef<Path>,
 {
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    Ok(normalize.read(open_input(filename)?, Blanks::Drop))
+}
+
+// Counts increases for both parts while only keeping the last three depths.
+fn stream_increases<I>(lines: I) -> io::Result<(usize, usize)>
+where
+    I: Iterator<Item = io::Result<String>>,
+{
+    let mut window: VecDeque<u32> = VecDeque::with_capacity(3);
+    let mut part_1 = 0;
+    let mut part_2 = 0;
+    for line in lines {
+        let depth = match line?.parse::<u32>() {
+            Ok(depth) => depth,
+            Err(_) => continue,
+        };
+        if window.back().is_some_and(|prev| *prev < depth) {
+            part_1 += 1;
+        }
+        if window.len() == 3 && window.pop_front().is_some_and(|first| first < depth) {
+            part_2 += 1;
+        }
+        window.push_back(depth);
+    }
+    Ok((part_1, part_2))
 }
 
 fn main() {
     let cli = Cli::from_args();
-    let file_lines = read_lines(cli.input);
+    if cli.compare.is_some() && cli.window.is_none() && cli.filters.is_empty() {
+        Error::with_description(
+            "--compare needs --window or --filter",
+            ErrorKind::MissingRequiredArgument,
+        )
+        .exit();
+    }
+    let config = Config::for_day(1);
+    let perf = cli.perf.recorder(&config);
+    let input = config.input(cli.input);
+    if cli.stream {
+        let normalize = &cli.normalize;
+        // One pass solves both parts, so it is timed as part 1
+        let parts = perf.part(1, || {
+            read_lines(input, normalize).and_then(stream_increases)
+        });
+        if let Ok((part_1, part_2)) = parts {
+            println!("Part 1: {:?}", part_1);
+            println!("Part 2: {:?}", part_2);
+        }
+        return;
+    }
+    let file_lines = read_lines(input, &cli.normalize);
     if let Ok(lines) = file_lines {
-        let data: Vec<u32> = lines
-            .filter_map(Result::ok)
-            .filter_map(|s| s.parse::<u32>().ok())
-            .collect();
-        let part_1 = data.windows(2).filter(|a| a.first() < a.last()).count();
-        let part_2 = data.windows(4).filter(|a| a.first() < a.last()).count();
+        let channels = Channels::parse(lines.filter_map(Result::ok));
+        if channels.columns.len() > 1 && cli.report {
+            for (name, data) in channels.names.iter().zip(&channels.columns) {
+                println!("{}:", name);
+                if let Some(report) = DepthReport::new(data, channels.skipped) {
+                    report.print();
+                }
+            }
+            return;
+        }
+        if channels.columns.len() > 1 || !cli.filters.is_empty() {
+            let comparison = cli.compare.unwrap_or(Comparison::Increases);
+            let filters = match cli.window {
+                Some(size) => vec![Filter::Sum(size)],
+                None if cli.filters.is_empty() => vec![Filter::Sum(1), Filter::Sum(3)],
+                None => cli.filters,
+            };
+            println!("{:?} per channel:", comparison);
+            print!("{:<12}", "Channel");
+            for filter in &filters {
+                print!("{:>12}", filter.to_string());
+            }
+            println!();
+            for (name, data) in channels.all() {
+                print!("{:<12}", name);
+                for filter in &filters {
+                    print!("{:>12}", filter.count(&data, comparison));
+                }
+                println!();
+            }
+            if channels.skipped > 0 {
+                println!("Skipped lines: {}", channels.skipped);
+            }
+            return;
+        }
+        let skipped = channels.skipped;
+        let data = channels.columns.into_iter().next().unwrap_or_default();
+        if cli.report {
+            match DepthReport::new(&data, skipped) {
+                Some(report) => report.print(),
+                None => println!("No readings, {} lines skipped", skipped),
+            }
+            return;
+        }
+        if let Some(size) = cli.window {
+            let comparison = cli.compare.unwrap_or(Comparison::Increases);
+            let events = window_events(&data, size, comparison);
+            println!(
+                "{:?} over windows of {}: {}",
+                comparison,
+                size,
+                events.len()
+            );
+            println!("Indices: {:?}", events);
+            return;
+        }
+        let part_1 = perf.part(1, || window_events(&data, 1, Comparison::Increases).len());
+        let part_2 = perf.part(2, || window_events(&data, 3, Comparison::Increases).len());
         println!("Part 1: {:?}", part_1);
         println!("Part 2: {:?}", part_2);
     }
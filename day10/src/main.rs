@@ -1,8 +1,10 @@
+use aoc::config::Config;
+use aoc::input::{open_input, Blanks, Lines, Normalize, NormalizeOpts};
+use aoc::perf::PerfOpts;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use std::collections::VecDeque;
 use std::collections::{BTreeMap, BTreeSet};
-use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
@@ -10,19 +12,31 @@ use structopt::StructOpt;
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Day10", about = "Syntax Scoring")]
 struct Cli {
-    /// Input file
+    /// Input file (or `-` for stdin), defaults to `input` in aoc.toml
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
+
+    /// Solve part 1 in a single pass without loading the whole input
+    #[structopt(long)]
+    stream: bool,
+
+    #[structopt(flatten)]
+    normalize: NormalizeOpts,
+
+    #[structopt(flatten)]
+    perf: PerfOpts,
 }
 
 // The output is wrapped in a Result to allow matching on errors
-// Returns an Iterator to the Reader of the lines of the file.
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+// Returns an Iterator to the normalised lines of the file.
+fn read_lines<P>(
+    filename: P,
+    normalize: &NormalizeOpts,
+) -> io::Result<Normalize<Lines<Box<dyn BufRead>>>>
 where
     P: AsRef<Path>,
 {
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    Ok(normalize.read(open_input(filename)?, Blanks::Drop))
 }
 
 lazy_static! {
@@ -36,7 +50,7 @@ lazy_static! {
     static ref END_CHARS: BTreeSet<char> = BTreeSet::from([')', ']', '}', '>']);
 }
 
-fn find_bad_end(run: &String) -> Option<char> {
+fn find_bad_end(run: &str) -> Option<char> {
     let mut stack: VecDeque<char> = VecDeque::new();
     for c in run.chars() {
         if START_CHARS.contains(&c) {
@@ -58,7 +72,7 @@ fn find_bad_end(run: &String) -> Option<char> {
 }
 
 #[allow(dead_code)]
-fn get_corrected_endings(run: &String) -> Option<String> {
+fn get_corrected_endings(run: &str) -> Option<String> {
     let mut stack: VecDeque<char> = VecDeque::new();
     for c in run.chars() {
         if START_CHARS.contains(&c) {
@@ -85,36 +99,60 @@ fn get_corrected_endings(run: &String) -> Option<String> {
     Some(r_str)
 }
 
+// Scores corrupted lines one at a time as they are read.
+fn stream_syntax_score<I>(lines: I) -> io::Result<u64>
+where
+    I: Iterator<Item = io::Result<String>>,
+{
+    let mut score = 0;
+    for line in lines {
+        if let Some(c) = find_bad_end(&line?) {
+            score += BAD_SCORE_TABLE.get(&c).unwrap_or(&0);
+        }
+    }
+    Ok(score)
+}
+
 fn main() {
     let cli = Cli::from_args();
-    let file_lines = read_lines(cli.input);
+    let config = Config::for_day(10);
+    let perf = cli.perf.recorder(&config);
+    let input = config.input(cli.input);
+    if cli.stream {
+        let normalize = &cli.normalize;
+        let part_1 = perf.part(1, || {
+            read_lines(input, normalize).and_then(stream_syntax_score)
+        });
+        if let Ok(part_1) = part_1 {
+            println!("Part 1: {:?}", part_1);
+        }
+        return;
+    }
+    let file_lines = read_lines(input, &cli.normalize);
     if let Ok(lines) = file_lines {
         let data: Vec<_> = lines.filter_map(Result::ok).collect();
-        let part_1: u64 = data
-            .iter()
-            .filter_map(|s| find_bad_end(s))
-            .filter_map(|c| BAD_SCORE_TABLE.get(&c))
-            .sum();
+        let part_1: u64 = perf.part(1, || {
+            data.iter()
+                .filter_map(|s| find_bad_end(s))
+                .filter_map(|c| BAD_SCORE_TABLE.get(&c))
+                .sum()
+        });
 
-        let part_2: Vec<u64> = data
-            .iter()
-            .filter_map(|s| {
-                if find_bad_end(s).is_some() {
-                    None
-                } else {
-                    Some(s)
-                }
-            })
-            .filter_map(|s| get_corrected_endings(s))
-            .map(|s| {
-                s.chars().fold(0_u64, |i, c| {
-                    (5 * i) + FIX_SCORE_TABLE.get(&c).unwrap_or(&0)
+        let part_2 = perf.part(2, || {
+            let part_2: Vec<u64> = data
+                .iter()
+                .filter(|s| find_bad_end(s).is_none())
+                .filter_map(|s| get_corrected_endings(s))
+                .map(|s| {
+                    s.chars().fold(0_u64, |i, c| {
+                        (5 * i) + FIX_SCORE_TABLE.get(&c).unwrap_or(&0)
+                    })
                 })
-            })
-            .sorted()
-            .collect();
+                .sorted()
+                .collect();
 
-        let part_2 = part_2[part_2.len() / 2];
+            part_2[part_2.len() / 2]
+        });
 
         println!("Part 1: {:?}", part_1);
         println!("Part 2: {:?}", part_2);
@@ -1,25 +1,41 @@
+use aoc::config::Config;
+use aoc::input::{Blanks, Lines, Normalize, NormalizeOpts};
+use aoc::perf::PerfOpts;
 use ndarray::Array2;
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io;
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Day11", about = "Dumbo Octopus")]
 struct Cli {
-    /// Input file
+    /// Input file, defaults to `input` in aoc.toml
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
+
+    /// Steps to count flashes over for part 1 [default: 100]
+    #[structopt(long)]
+    steps: Option<usize>,
+
+    #[structopt(flatten)]
+    normalize: NormalizeOpts,
+
+    #[structopt(flatten)]
+    perf: PerfOpts,
 }
 
 // The output is wrapped in a Result to allow matching on errors
-// Returns an Iterator to the Reader of the lines of the file.
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+// Returns an Iterator to the normalised lines of the file.
+fn read_lines<P>(
+    filename: P,
+    normalize: &NormalizeOpts,
+) -> io::Result<Normalize<Lines<io::BufReader<File>>>>
 where
     P: AsRef<Path>,
 {
     let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    Ok(normalize.read(io::BufReader::new(file), Blanks::Drop))
 }
 
 const GRID_SIZE: usize = 10;
@@ -101,7 +117,11 @@ fn step_grid(grid: &mut Array2<Octopus>) {
 
 fn main() {
     let cli = Cli::from_args();
-    let file_lines = read_lines(cli.input);
+    let config = Config::for_day(11);
+    let perf = cli.perf.recorder(&config);
+    let input = config.input(cli.input);
+    let steps = cli.steps.or_else(|| config.get("steps")).unwrap_or(100);
+    let file_lines = read_lines(input, &cli.normalize);
     if let Ok(lines) = file_lines {
         let data: Array2<Octopus> = Array2::from_shape_vec(
             (GRID_SIZE, GRID_SIZE),
@@ -113,20 +133,25 @@ fn main() {
         )
         .unwrap();
 
-        let mut mut_grid = data.clone();
-        for _ in 0..100 {
-            step_grid(&mut mut_grid);
-            mut_grid.map_mut(Octopus::reset);
-        }
-        let part_1: u32 = mut_grid.iter().map(|o| o.flashes).sum();
-
-        let mut mut_grid = data;
-        let mut part_2: u32 = 0;
-        while mut_grid.iter().map(|o| o.energy_level as u32).sum::<u32>() != 0 {
-            step_grid(&mut mut_grid);
-            mut_grid.map_mut(Octopus::reset);
-            part_2 += 1;
-        }
+        let part_1: u32 = perf.part(1, || {
+            let mut mut_grid = data.clone();
+            for _ in 0..steps {
+                step_grid(&mut mut_grid);
+                mut_grid.map_mut(Octopus::reset);
+            }
+            mut_grid.iter().map(|o| o.flashes).sum()
+        });
+
+        let part_2: u32 = perf.part(2, || {
+            let mut mut_grid = data;
+            let mut steps: u32 = 0;
+            while mut_grid.iter().map(|o| o.energy_level as u32).sum::<u32>() != 0 {
+                step_grid(&mut mut_grid);
+                mut_grid.map_mut(Octopus::reset);
+                steps += 1;
+            }
+            steps
+        });
 
         println!("Part 1: {:?}", part_1);
         println!("Part 2: {:?}", part_2);
@@ -1,25 +1,37 @@
+use aoc::config::Config;
+use aoc::input::{Blanks, Lines, Normalize, NormalizeOpts};
+use aoc::perf::PerfOpts;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io;
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Day12", about = "Passage Pathing")]
 struct Cli {
-    /// Input file
+    /// Input file, defaults to `input` in aoc.toml
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    normalize: NormalizeOpts,
+
+    #[structopt(flatten)]
+    perf: PerfOpts,
 }
 
 // The output is wrapped in a Result to allow matching on errors
-// Returns an Iterator to the Reader of the lines of the file.
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+// Returns an Iterator to the normalised lines of the file.
+fn read_lines<P>(
+    filename: P,
+    normalize: &NormalizeOpts,
+) -> io::Result<Normalize<Lines<io::BufReader<File>>>>
 where
     P: AsRef<Path>,
 {
     let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    Ok(normalize.read(io::BufReader::new(file), Blanks::Drop))
 }
 
 #[derive(Debug, Clone)]
@@ -123,16 +135,19 @@ fn count_routes(graph: &Graph) -> usize {
 
 fn main() {
     let cli = Cli::from_args();
-    let file_lines = read_lines(cli.input);
+    let config = Config::for_day(12);
+    let perf = cli.perf.recorder(&config);
+    let input = config.input(cli.input);
+    let file_lines = read_lines(input, &cli.normalize);
     if let Ok(lines) = file_lines {
         let mut data: Graph = lines.filter_map(Result::ok).fold(Graph::new(), |mut g, s| {
             g.add(s);
             g
         });
         data.marked = true;
-        let part_1 = count_routes(&data);
+        let part_1 = perf.part(1, || count_routes(&data));
         data.marked = false;
-        let part_2 = count_routes(&data);
+        let part_2 = perf.part(2, || count_routes(&data));
         println!("Part 1: {:?}", part_1);
         println!("Part 2: {:?}", part_2);
     }
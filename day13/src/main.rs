@@ -1,28 +1,66 @@
+use aoc::config::Config;
+use aoc::input::{Blanks, Lines, Normalize, NormalizeOpts};
+use aoc::perf::PerfOpts;
 use ndarray::{s, Array, Array2, ArrayView2};
 use std::fmt::Debug;
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io;
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Day13", about = "Transparent Origami")]
 struct Cli {
-    /// Input file
+    /// Input file, defaults to `input` in aoc.toml
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    normalize: NormalizeOpts,
+
+    #[structopt(flatten)]
+    perf: PerfOpts,
 }
 
 // The output is wrapped in a Result to allow matching on errors
-// Returns an Iterator to the Reader of the lines of the file.
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+// Returns an Iterator to the normalised lines of the file.
+fn read_lines<P>(
+    filename: P,
+    normalize: &NormalizeOpts,
+) -> io::Result<Normalize<Lines<io::BufReader<File>>>>
 where
     P: AsRef<Path>,
 {
     let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    Ok(normalize.read(io::BufReader::new(file), Blanks::Collapse))
 }
 
+const GLYPH_WIDTH: usize = 4;
+const GLYPH_HEIGHT: usize = 6;
+const GLYPH_PITCH: usize = GLYPH_WIDTH + 1;
+
+#[rustfmt::skip]
+const FONT: [(char, [&str; GLYPH_HEIGHT]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+    (' ', ["....", "....", "....", "....", "....", "...."]),
+];
+
 #[derive(Debug, Clone, Copy)]
 enum FoldDirection {
     X,
@@ -74,9 +112,36 @@ fn print_grid(grid: &ArrayView2<u8>) {
     }
 }
 
+// Reads the folded paper as letters in the 4x6 puzzle font, with one glyph
+// every five columns. Glyphs that are not in the font come back as `None`.
+fn read_letters(grid: &ArrayView2<u8>) -> Vec<Option<char>> {
+    let (width, height) = grid.dim();
+    let glyphs = width.div_ceil(GLYPH_PITCH);
+    let lit = |x: usize, y: usize| grid.get((x, y)).is_some_and(|b| *b > 0);
+    (0..glyphs)
+        .map(|g| {
+            if height != GLYPH_HEIGHT {
+                return None;
+            }
+            FONT.iter()
+                .find(|(_, rows)| {
+                    rows.iter().enumerate().all(|(y, row)| {
+                        row.chars()
+                            .enumerate()
+                            .all(|(x, c)| lit(g * GLYPH_PITCH + x, y) == (c == '#'))
+                    }) && (0..GLYPH_HEIGHT).all(|y| !lit(g * GLYPH_PITCH + GLYPH_WIDTH, y))
+                })
+                .map(|(c, _)| *c)
+        })
+        .collect()
+}
+
 fn main() {
     let cli = Cli::from_args();
-    let file_lines = read_lines(cli.input);
+    let config = Config::for_day(13);
+    let perf = cli.perf.recorder(&config);
+    let input = config.input(cli.input);
+    let file_lines = read_lines(input, &cli.normalize);
     if let Ok(lines) = file_lines {
         let mut data = lines.filter_map(Result::ok);
         let points: Vec<(usize, usize)> = data
@@ -95,14 +160,22 @@ fn main() {
             *grid.get_mut(point).unwrap() = 1;
         }
         let folds: Vec<_> = data.map(PaperFold::new).collect();
-        let part_1 = folds[0]
-            .paper_fold(grid.clone())
-            .iter()
-            .filter(|i| **i > 0)
-            .count();
-        let part_2 = &folds.iter().fold(grid, |g, f| f.paper_fold(g));
+        let part_1 = perf.part(1, || {
+            folds[0]
+                .paper_fold(grid.clone())
+                .iter()
+                .filter(|i| **i > 0)
+                .count()
+        });
+        let part_2 = &perf.part(2, || folds.iter().fold(grid, |g, f| f.paper_fold(g)));
         println!("Part 1: {:?}", part_1);
-        println!("Part 2:");
-        print_grid(&part_2.view());
+        let letters = read_letters(&part_2.view());
+        let text: String = letters.iter().map(|c| c.unwrap_or('?')).collect();
+        if !letters.is_empty() && letters.iter().all(Option::is_some) {
+            println!("Part 2: {}", text.trim());
+        } else {
+            println!("Part 2: {} (unrecognised glyphs shown as ?)", text);
+            print_grid(&part_2.view());
+        }
     }
 }
@@ -1,7 +1,10 @@
+use aoc::config::Config;
+use aoc::input::{Blanks, Lines, Normalize, NormalizeOpts};
+use aoc::perf::PerfOpts;
 use itertools::Itertools;
 use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io;
 use std::ops::AddAssign;
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
@@ -9,19 +12,36 @@ use structopt::StructOpt;
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Day13", about = "Transparent Origami")]
 struct Cli {
-    /// Input file
+    /// Input file, defaults to `input` in aoc.toml
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
+
+    /// Insertion steps for part 1 [default: 10]
+    #[structopt(long)]
+    part_1_steps: Option<usize>,
+
+    /// Insertion steps for part 2 [default: 40]
+    #[structopt(long)]
+    part_2_steps: Option<usize>,
+
+    #[structopt(flatten)]
+    normalize: NormalizeOpts,
+
+    #[structopt(flatten)]
+    perf: PerfOpts,
 }
 
 // The output is wrapped in a Result to allow matching on errors
-// Returns an Iterator to the Reader of the lines of the file.
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+// Returns an Iterator to the normalised lines of the file.
+fn read_lines<P>(
+    filename: P,
+    normalize: &NormalizeOpts,
+) -> io::Result<Normalize<Lines<io::BufReader<File>>>>
 where
     P: AsRef<Path>,
 {
     let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    Ok(normalize.read(io::BufReader::new(file), Blanks::Collapse))
 }
 
 fn build_polymer(
@@ -69,7 +89,18 @@ fn solution(counts: &BTreeMap<char, usize>) -> usize {
 
 fn main() {
     let cli = Cli::from_args();
-    let file_lines = read_lines(cli.input);
+    let config = Config::for_day(14);
+    let perf = cli.perf.recorder(&config);
+    let input = config.input(cli.input);
+    let part_1_steps = cli
+        .part_1_steps
+        .or_else(|| config.get("part_1_steps"))
+        .unwrap_or(10);
+    let part_2_steps = cli
+        .part_2_steps
+        .or_else(|| config.get("part_2_steps"))
+        .unwrap_or(40);
+    let file_lines = read_lines(input, &cli.normalize);
     if let Ok(lines) = file_lines {
         let mut data = lines.filter_map(Result::ok);
         let polymer: String = data.next().unwrap().clone();
@@ -84,8 +115,12 @@ fn main() {
                 );
                 tree
             });
-        let part_1 = solution(&build_polymer(polymer.clone(), &data, 10));
-        let part_2 = solution(&build_polymer(polymer.clone(), &data, 40));
+        let part_1 = perf.part(1, || {
+            solution(&build_polymer(polymer.clone(), &data, part_1_steps))
+        });
+        let part_2 = perf.part(2, || {
+            solution(&build_polymer(polymer.clone(), &data, part_2_steps))
+        });
         println!("Part 1: {}", part_1);
         println!("Part 2: {}", part_2);
     }
@@ -1,102 +1,800 @@
+use aoc::config::Config;
+use aoc::input::{open_input, Blanks, Lines, Normalize, NormalizeOpts};
+use aoc::perf::PerfOpts;
+use script::Script;
+use std::fmt;
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io::{self, BufRead, Write};
 use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
 use structopt::StructOpt;
 
+mod script;
+
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Day2", about = "Dive!")]
 struct Cli {
-    /// Input file
+    /// Input file (or `-` for stdin), defaults to `input` in aoc.toml
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
+
+    /// Solve in a single pass without loading the whole input
+    #[structopt(long)]
+    stream: bool,
+
+    /// Write every location of both models to this CSV file
+    #[structopt(long, parse(from_os_str), conflicts_with = "stream")]
+    csv: Option<PathBuf>,
+
+    /// Plot the depth profile of both models to this SVG file
+    #[structopt(long, parse(from_os_str), conflicts_with = "stream")]
+    svg: Option<PathBuf>,
+
+    /// Print a shortest course to `DEPTH,POSITION[,AIM]` for both models
+    /// instead of reading an input
+    #[structopt(
+        long,
+        allow_hyphen_values = true,
+        conflicts_with_all = &["stream", "csv", "svg"]
+    )]
+    plan: Option<Target>,
+
+    /// Largest amount a planned `forward` may use
+    #[structopt(long, default_value = "9", parse(try_from_str = parse_limit))]
+    max_forward: i32,
+
+    /// Largest amount a planned `down` or `up` may use
+    #[structopt(long, default_value = "9", parse(try_from_str = parse_limit))]
+    max_turn: i32,
+
+    /// Print the input compiled down to plain commands instead of solving
+    #[structopt(long, conflicts_with_all = &["stream", "csv", "svg", "plan"])]
+    expand: bool,
+
+    #[structopt(flatten)]
+    normalize: NormalizeOpts,
+
+    #[structopt(flatten)]
+    perf: PerfOpts,
+}
+
+fn parse_limit(s: &str) -> Result<i32, String> {
+    match s.parse::<i32>() {
+        Ok(limit) if limit >= 1 => Ok(limit),
+        _ => Err(format!("limit `{}` is not a whole number of at least 1", s)),
+    }
 }
 
 // The output is wrapped in a Result to allow matching on errors
-// Returns an Iterator to the Reader of the lines of the file.
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+// Returns an Iterator to the normalised lines of the file.
+fn read_lines<P>(
+    filename: P,
+    normalize: &NormalizeOpts,
+) -> io::Result<Normalize<Lines<Box<dyn BufRead>>>>
+where
+    P: AsRef<Path>,
+{
+    Ok(normalize.read(open_input(filename)?, Blanks::Drop))
+}
+
+// Returns an Iterator to the commands of the file, compiled as a course script
+// with errors pointing at the line of the original file.
+fn read_script<P>(
+    filename: P,
+    normalize: &NormalizeOpts,
+) -> io::Result<Script<impl Iterator<Item = io::Result<(usize, String)>>>>
 where
     P: AsRef<Path>,
 {
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    let mut lines = read_lines(filename, normalize)?;
+    let numbered = std::iter::from_fn(move || {
+        let line = lines.next()?;
+        Some(line.map(|line| (lines.line_no(), line)))
+    });
+    Ok(Script::new(numbered))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Forward(i32),
+    Down(i32),
+    Up(i32),
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (verb, amount) = s
+            .split_once(' ')
+            .ok_or_else(|| format!("expected `<command> <amount>`, got `{}`", s))?;
+        let amount = amount
+            .parse::<i32>()
+            .map_err(|e| format!("bad amount `{}`: {}", amount, e))?;
+        match verb {
+            "forward" => Ok(Command::Forward(amount)),
+            "down" => Ok(Command::Down(amount)),
+            "up" => Ok(Command::Up(amount)),
+            _ => Err(format!("unknown command `{}`", verb)),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Forward(d) => write!(f, "forward {}", d),
+            Command::Down(d) => write!(f, "down {}", d),
+            Command::Up(d) => write!(f, "up {}", d),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Location {
     depth: i32,
     position: i32,
     aim: i32,
 }
 
-impl Location {
-    fn next_location(&self, instruction: &String) -> Location {
-        let data = instruction.split_once(' ').unwrap();
-        let i = data.0;
-        let d = data.1.parse::<i32>().unwrap();
-        match i {
-            "forward" => Location {
-                depth: self.depth + d,
-                position: self.position,
-                aim: self.aim,
+/// The field that left the range of an `i32` while applying a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Overflow {
+    Depth,
+    Position,
+    Aim,
+}
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Overflow::Depth => write!(f, "depth overflow"),
+            Overflow::Position => write!(f, "position overflow"),
+            Overflow::Aim => write!(f, "aim overflow"),
+        }
+    }
+}
+
+/// How a submarine moves in response to each command.
+trait SubmarineModel {
+    fn name(&self) -> &'static str;
+
+    fn apply(&self, location: Location, command: Command) -> Result<Location, Overflow>;
+
+    /// Finds a shortest list of commands that takes the submarine from the
+    /// origin to `target`, or explains why there is none.
+    fn plan(&self, _target: Target, _limits: Limits) -> Result<Vec<Command>, String> {
+        Err("this model has no planner".to_string())
+    }
+}
+
+/// `down` and `up` change the depth directly.
+struct Direct;
+
+impl SubmarineModel for Direct {
+    fn name(&self) -> &'static str {
+        "direct"
+    }
+
+    fn apply(&self, location: Location, command: Command) -> Result<Location, Overflow> {
+        Ok(match command {
+            Command::Forward(d) => Location {
+                position: location.position.checked_add(d).ok_or(Overflow::Position)?,
+                ..location
             },
-            "down" => Location {
-                depth: self.depth,
-                position: self.position + d,
-                aim: self.aim,
+            Command::Down(d) => Location {
+                depth: location.depth.checked_add(d).ok_or(Overflow::Depth)?,
+                ..location
             },
-            "up" => Location {
-                depth: self.depth,
-                position: self.position - d,
-                aim: self.aim,
+            Command::Up(d) => Location {
+                depth: location.depth.checked_sub(d).ok_or(Overflow::Depth)?,
+                ..location
             },
-            _ => *self,
+        })
+    }
+
+    // Forwards and turns are independent, so each is covered greedily.
+    fn plan(&self, target: Target, limits: Limits) -> Result<Vec<Command>, String> {
+        if target.aim.is_some_and(|aim| aim != 0) {
+            return Err("the aim never changes from 0".to_string());
         }
+        let mut commands = forwards(target.position as i64, limits)?;
+        commands.extend(turns(target.depth as i64, limits));
+        Ok(commands)
     }
+}
+
+/// `down` and `up` change the aim, and moving forward dives along it.
+struct Aimed;
 
-    fn next_location_aim(&self, instruction: &String) -> Location {
-        let data = instruction.split_once(' ').unwrap();
-        let i = data.0;
-        let d = data.1.parse::<i32>().unwrap();
-        match i {
-            "forward" => Location {
-                depth: self.depth + (self.aim * d),
-                position: self.position + d,
-                aim: self.aim,
+impl SubmarineModel for Aimed {
+    fn name(&self) -> &'static str {
+        "aimed"
+    }
+
+    fn apply(&self, location: Location, command: Command) -> Result<Location, Overflow> {
+        Ok(match command {
+            Command::Forward(d) => Location {
+                depth: location
+                    .aim
+                    .checked_mul(d)
+                    .and_then(|dive| location.depth.checked_add(dive))
+                    .ok_or(Overflow::Depth)?,
+                position: location.position.checked_add(d).ok_or(Overflow::Position)?,
+                ..location
             },
-            "down" => Location {
-                depth: self.depth,
-                position: self.position,
-                aim: self.aim + d,
+            Command::Down(d) => Location {
+                aim: location.aim.checked_add(d).ok_or(Overflow::Aim)?,
+                ..location
             },
-            "up" => Location {
-                depth: self.depth,
-                position: self.position,
-                aim: self.aim - d,
+            Command::Up(d) => Location {
+                aim: location.aim.checked_sub(d).ok_or(Overflow::Aim)?,
+                ..location
             },
-            _ => *self,
+        })
+    }
+
+    fn plan(&self, target: Target, limits: Limits) -> Result<Vec<Command>, String> {
+        let (position, depth) = (target.position as i64, target.depth as i64);
+        if position < 0 {
+            return Err("forward can only increase the position".to_string());
+        }
+        if position == 0 && depth != 0 {
+            return Err("the depth only changes while moving forward".to_string());
+        }
+        let search = AimSearch {
+            max_forward: limits.max_forward as i64,
+            max_turn: limits.max_turn as i64,
+            aim: target.aim.map(i64::from),
+        };
+        let mut checkpoints = Vec::new();
+        if position > 0 {
+            let mut budget =
+                blocks(position, search.max_forward) + search.turn_bound(position, depth, 0);
+            while !search.search(position, depth, 0, budget, true, &mut checkpoints) {
+                budget += 1;
+            }
+        }
+        let mut commands = Vec::new();
+        let mut aim = 0;
+        for (i, (remaining, delta)) in checkpoints.iter().enumerate() {
+            let next = checkpoints.get(i + 1).map_or(0, |c| c.0);
+            commands.extend(turns(*delta, limits));
+            commands.extend(forwards(remaining - next, limits)?);
+            aim += delta;
+        }
+        if let Some(target_aim) = search.aim {
+            commands.extend(turns(target_aim - aim, limits));
+        }
+        Ok(commands)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Target {
+    depth: i32,
+    position: i32,
+    aim: Option<i32>,
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|v| {
+                v.trim()
+                    .parse::<i32>()
+                    .map_err(|e| format!("`{}`: {}", v, e))
+            })
+            .collect::<Result<Vec<i32>, String>>()?;
+        match values[..] {
+            [depth, position] => Ok(Target {
+                depth,
+                position,
+                aim: None,
+            }),
+            [depth, position, aim] => Ok(Target {
+                depth,
+                position,
+                aim: Some(aim),
+            }),
+            _ => Err("expected DEPTH,POSITION[,AIM]".to_string()),
+        }
+    }
+}
+
+impl Target {
+    fn reached_by(&self, location: Location) -> bool {
+        location.depth == self.depth
+            && location.position == self.position
+            && self.aim.is_none_or(|aim| aim == location.aim)
+    }
+}
+
+/// The largest amount a planned command may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Limits {
+    max_forward: i32,
+    max_turn: i32,
+}
+
+// The fewest commands of at most `max` needed to move `amount` either way.
+fn blocks(amount: i64, max: i64) -> i64 {
+    amount.unsigned_abs().div_ceil(max as u64) as i64
+}
+
+// Splits `amount` into as few commands of at most `max` as possible.
+fn split<F>(amount: i64, max: i32, command: F) -> Vec<Command>
+where
+    F: Fn(i32) -> Command,
+{
+    if amount == 0 {
+        return Vec::new();
+    }
+    let count = blocks(amount, max as i64);
+    (0..count)
+        .map(|i| command((amount.abs() - i * max as i64).min(max as i64) as i32))
+        .collect()
+}
+
+fn forwards(amount: i64, limits: Limits) -> Result<Vec<Command>, String> {
+    if amount < 0 {
+        return Err("forward can only increase the position".to_string());
+    }
+    Ok(split(amount, limits.max_forward, Command::Forward))
+}
+
+fn turns(amount: i64, limits: Limits) -> Vec<Command> {
+    let turn = if amount < 0 {
+        Command::Up
+    } else {
+        Command::Down
+    };
+    split(amount, limits.max_turn, turn)
+}
+
+// Under the aimed model every change of aim is multiplied into the depth by
+// the distance still left to travel. A course is therefore a list of
+// checkpoints, each a remaining distance and the net change of aim made there,
+// and costs the forwards between the checkpoints plus the turns at each one.
+//
+// `search` is an iterative deepening step: it looks for a course within
+// `budget` commands, and every cut it makes is a lower bound, so the first
+// budget that succeeds is the shortest.
+struct AimSearch {
+    max_forward: i64,
+    max_turn: i64,
+    aim: Option<i64>,
+}
+
+impl AimSearch {
+    // The fewest turns left to reach the target aim from `aim`.
+    fn fix(&self, aim: i64) -> i64 {
+        self.aim
+            .map_or(0, |target| blocks(target - aim, self.max_turn))
+    }
+
+    // A lower bound on the turns still needed with `remaining` distance left.
+    // Descending `depth` takes a total rise of at least depth / remaining, and
+    // everything risen beyond the target aim has to be turned back again.
+    fn turn_bound(&self, remaining: i64, depth: i64, aim: i64) -> i64 {
+        let change = match self.aim {
+            Some(target) => target - aim,
+            None => return blocks(depth, self.max_turn * remaining),
+        };
+        let (depth, change) = if depth < 0 {
+            (-depth, -change)
+        } else {
+            (depth, change)
+        };
+        let rise = blocks(depth, remaining).max(change).max(0);
+        blocks(rise, self.max_turn) + blocks(rise - change, self.max_turn)
+    }
+
+    fn search(
+        &self,
+        remaining: i64,
+        depth: i64,
+        aim: i64,
+        budget: i64,
+        root: bool,
+        checkpoints: &mut Vec<(i64, i64)>,
+    ) -> bool {
+        let turns = budget - blocks(remaining, self.max_forward);
+        if turns < self.turn_bound(remaining, depth, aim) {
+            return false;
+        }
+        if depth % remaining == 0 {
+            let delta = depth / remaining;
+            let fix = self.fix(aim + delta);
+            if (delta != 0 || root) && blocks(delta, self.max_turn) + fix <= turns {
+                checkpoints.push((remaining, delta));
+                return true;
+            }
+        }
+        // Checkpoints after the first only exist to turn, so their change of
+        // aim is never zero. Deltas leaving the least depth are tried first.
+        let reach = turns * self.max_turn;
+        let mut deltas: Vec<i64> = (-reach..=reach).filter(|d| root || *d != 0).collect();
+        deltas.sort_by_key(|d| (depth - d * remaining).abs());
+        for delta in deltas {
+            let left = turns - blocks(delta, self.max_turn);
+            let rest = depth - delta * remaining;
+            if rest == 0
+                || rest.abs() > left * self.max_turn * (remaining - 1)
+                || left < self.turn_bound(remaining - 1, rest, aim + delta)
+            {
+                continue;
+            }
+            let lowest = blocks(rest, left * self.max_turn).max(1);
+            // Without a target aim the largest distances leave the most room.
+            // With one, the aim still to change is best spent where it lands
+            // exactly on the depth still to descend.
+            let mut nexts: Vec<i64> = (lowest..remaining).rev().collect();
+            if let Some(target) = self.aim {
+                let change = target - aim - delta;
+                if change != 0 {
+                    nexts.sort_by_key(|next| (rest - change * next).abs());
+                }
+            }
+            checkpoints.push((remaining, delta));
+            for next in nexts {
+                let gap = blocks(remaining - next, self.max_forward);
+                let extra =
+                    gap + blocks(next, self.max_forward) - blocks(remaining, self.max_forward);
+                if left - extra < self.turn_bound(next, rest, aim + delta) {
+                    continue;
+                }
+                let budget = budget - blocks(delta, self.max_turn) - gap;
+                if self.search(next, rest, aim + delta, budget, false, checkpoints) {
+                    return true;
+                }
+            }
+            checkpoints.pop();
+        }
+        false
+    }
+}
+
+fn run_course<M>(model: &M, init_loc: Location, commands: &[Command]) -> Result<Location, Overflow>
+where
+    M: SubmarineModel + ?Sized,
+{
+    commands
+        .iter()
+        .try_fold(init_loc, |cur, command| model.apply(cur, *command))
+}
+
+fn print_part(part: usize, location: Result<Location, Overflow>) {
+    match location {
+        Ok(l) => println!("Part {}: {:?}", part, l.depth as i64 * l.position as i64),
+        Err(e) => println!("Part {}: {}", part, e),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Event {
+    AboveSurface,
+    Overflow(Overflow),
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::AboveSurface => write!(f, "above surface"),
+            Event::Overflow(o) => write!(f, "{}", o),
         }
     }
 }
 
+#[derive(Debug, Clone)]
+struct Step {
+    command: Option<Command>,
+    location: Location,
+    event: Option<Event>,
+}
+
+/// Every location a model passes through, starting from the initial one. An
+/// overflow ends the trajectory, flagged on the last step.
+#[derive(Debug, Clone)]
+struct Trajectory {
+    model: &'static str,
+    steps: Vec<Step>,
+}
+
+impl Trajectory {
+    fn record<M>(model: &M, init_loc: Location, commands: &[Command]) -> Trajectory
+    where
+        M: SubmarineModel + ?Sized,
+    {
+        let mut steps = vec![Step {
+            command: None,
+            location: init_loc,
+            event: None,
+        }];
+        let mut location = init_loc;
+        for command in commands {
+            let (next, event) = match model.apply(location, *command) {
+                Ok(next) if next.depth < 0 => (next, Some(Event::AboveSurface)),
+                Ok(next) => (next, None),
+                Err(o) => (location, Some(Event::Overflow(o))),
+            };
+            steps.push(Step {
+                command: Some(*command),
+                location: next,
+                event,
+            });
+            if let Some(Event::Overflow(_)) = event {
+                break;
+            }
+            location = next;
+        }
+        Trajectory {
+            model: model.name(),
+            steps,
+        }
+    }
+
+    fn print_events(&self) {
+        let mut counts: Vec<(Event, usize, usize)> = Vec::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            if let Some(event) = step.event {
+                match counts.iter_mut().find(|(e, _, _)| *e == event) {
+                    Some((_, count, _)) => *count += 1,
+                    None => counts.push((event, 1, i)),
+                }
+            }
+        }
+        for (event, count, first) in counts {
+            println!(
+                "{}: {} on {} step(s), first at step {}",
+                self.model, event, count, first
+            );
+        }
+    }
+}
+
+fn write_csv<W: Write>(out: &mut W, trajectories: &[Trajectory]) -> io::Result<()> {
+    writeln!(out, "model,step,command,position,depth,aim,event")?;
+    for trajectory in trajectories {
+        for (i, step) in trajectory.steps.iter().enumerate() {
+            let command = match step.command {
+                Some(Command::Forward(d)) => format!("forward {}", d),
+                Some(Command::Down(d)) => format!("down {}", d),
+                Some(Command::Up(d)) => format!("up {}", d),
+                None => String::new(),
+            };
+            let event = step.event.map(|e| e.to_string()).unwrap_or_default();
+            writeln!(
+                out,
+                "{},{},{},{},{},{},{}",
+                trajectory.model,
+                i,
+                command,
+                step.location.position,
+                step.location.depth,
+                step.location.aim,
+                event
+            )?;
+        }
+    }
+    Ok(())
+}
+
+const PLOT_WIDTH: f64 = 800.0;
+const PANEL_HEIGHT: f64 = 300.0;
+const MARGIN: f64 = 40.0;
+
+// Draws one depth profile panel per trajectory, position across and depth
+// down, each scaled to its own range with the surface drawn dashed.
+fn write_svg<W: Write>(out: &mut W, trajectories: &[Trajectory]) -> io::Result<()> {
+    let panel = PANEL_HEIGHT + 2.0 * MARGIN;
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="12">"#,
+        PLOT_WIDTH + 2.0 * MARGIN,
+        panel * trajectories.len() as f64
+    )?;
+    for (n, trajectory) in trajectories.iter().enumerate() {
+        let locations = trajectory.steps.iter().map(|s| s.location);
+        let max_x = locations
+            .clone()
+            .map(|l| l.position)
+            .max()
+            .unwrap_or(0)
+            .max(1) as f64;
+        let min_y = locations.clone().map(|l| l.depth).min().unwrap_or(0).min(0) as f64;
+        let max_y = locations.map(|l| l.depth).max().unwrap_or(0).max(1) as f64;
+        let top = n as f64 * panel + MARGIN;
+        let x = |p: i32| MARGIN + p as f64 / max_x * PLOT_WIDTH;
+        let y = |d: i32| top + (d as f64 - min_y) / (max_y - min_y) * PANEL_HEIGHT;
+        writeln!(
+            out,
+            r#"<text x="{}" y="{}">{} (depth {} to {}, position 0 to {})</text>"#,
+            MARGIN,
+            top - 10.0,
+            trajectory.model,
+            min_y,
+            max_y,
+            max_x
+        )?;
+        writeln!(
+            out,
+            r#"<line x1="{}" y1="{2}" x2="{}" y2="{2}" stroke="steelblue" stroke-dasharray="4"/>"#,
+            MARGIN,
+            MARGIN + PLOT_WIDTH,
+            y(0)
+        )?;
+        let points: Vec<String> = trajectory
+            .steps
+            .iter()
+            .map(|s| format!("{:.1},{:.1}", x(s.location.position), y(s.location.depth)))
+            .collect();
+        writeln!(
+            out,
+            r#"<polyline fill="none" stroke="black" points="{}"/>"#,
+            points.join(" ")
+        )?;
+        for step in trajectory.steps.iter().filter(|s| s.event.is_some()) {
+            writeln!(
+                out,
+                r#"<circle cx="{:.1}" cy="{:.1}" r="3" fill="red"/>"#,
+                x(step.location.position),
+                y(step.location.depth)
+            )?;
+        }
+    }
+    writeln!(out, "</svg>")
+}
+
+fn write_file<F>(path: &Path, trajectories: &[Trajectory], write: F)
+where
+    F: Fn(&mut io::BufWriter<File>, &[Trajectory]) -> io::Result<()>,
+{
+    let result = File::create(path).and_then(|file| {
+        let mut out = io::BufWriter::new(file);
+        write(&mut out, trajectories)?;
+        out.flush()
+    });
+    if let Err(e) = result {
+        eprintln!("Unable to write {}: {}", path.display(), e);
+    }
+}
+
+// Runs every model over the commands as they are read.
+fn stream_course<I>(
+    commands: I,
+    models: &[&dyn SubmarineModel],
+    init_loc: Location,
+) -> io::Result<Vec<Result<Location, Overflow>>>
+where
+    I: Iterator<Item = io::Result<Command>>,
+{
+    let mut locations = vec![Ok(init_loc); models.len()];
+    for command in commands {
+        let command = command?;
+        for (model, location) in models.iter().zip(locations.iter_mut()) {
+            *location = location.and_then(|l| model.apply(l, command));
+        }
+    }
+    Ok(locations)
+}
+
+// Prints a planned course after checking it really ends at the target. A
+// course that misses is a planner bug, reported as an error.
+fn print_plan(model: &dyn SubmarineModel, target: Target, limits: Limits) -> Result<(), String> {
+    let init_loc = Location {
+        depth: 0,
+        position: 0,
+        aim: 0,
+    };
+    match model.plan(target, limits) {
+        Ok(commands) => {
+            let reached = run_course(model, init_loc, &commands);
+            if !reached.as_ref().is_ok_and(|l| target.reached_by(*l)) {
+                return Err(format!(
+                    "planned course for the {} model ends at {:?}",
+                    model.name(),
+                    reached
+                ));
+            }
+            println!(
+                "Course for the {} model: {} commands",
+                model.name(),
+                commands.len()
+            );
+            for command in commands {
+                println!("{}", command);
+            }
+        }
+        Err(reason) => println!("No course for the {} model: {}", model.name(), reason),
+    }
+    Ok(())
+}
+
 fn main() {
     let cli = Cli::from_args();
-    let file_lines = read_lines(cli.input);
+    let config = Config::for_day(2);
+    let perf = cli.perf.recorder(&config);
+    let models: [&dyn SubmarineModel; 2] = [&Direct, &Aimed];
+    if let Some(target) = cli.plan {
+        let limits = Limits {
+            max_forward: cli.max_forward,
+            max_turn: cli.max_turn,
+        };
+        let mut failed = false;
+        for model in models {
+            if let Err(e) = print_plan(model, target, limits) {
+                eprintln!("{}", e);
+                failed = true;
+            }
+        }
+        if failed {
+            process::exit(1);
+        }
+        return;
+    }
+    let input = config.input(cli.input);
     let init_loc = Location {
         depth: 0,
         position: 0,
         aim: 0,
     };
-    if let Ok(lines) = file_lines {
-        let data: Vec<String> = lines.filter_map(Result::ok).collect();
-
-        let part_1 = data
-            .iter()
-            .fold(init_loc, |cur, line| cur.next_location(line));
-        let part_2 = data
-            .iter()
-            .fold(init_loc, |cur, line| cur.next_location_aim(line));
-
-        println!("Part 1: {:?}", part_1.depth * part_1.position);
-        println!("Part 2: {:?}", part_2.depth * part_2.position);
+    if cli.stream {
+        let normalize = &cli.normalize;
+        // One pass solves both parts, so it is timed as part 1
+        let locations = perf.part(1, || {
+            read_script(input, normalize).and_then(|c| stream_course(c, &models, init_loc))
+        });
+        match locations {
+            Ok(locations) => {
+                for (part, location) in locations.into_iter().enumerate() {
+                    print_part(part + 1, location);
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+        return;
+    }
+    if cli.expand {
+        let result = read_script(input, &cli.normalize).and_then(|commands| {
+            let mut out = io::BufWriter::new(io::stdout().lock());
+            for command in commands {
+                writeln!(out, "{}", command?)?;
+            }
+            out.flush()
+        });
+        if let Err(e) = result {
+            eprintln!("{}", e);
+        }
+        return;
+    }
+    let commands: Vec<Command> = match read_script(input, &cli.normalize).and_then(|c| c.collect())
+    {
+        Ok(commands) => commands,
+        Err(e) => {
+            eprintln!("{}", e);
+            return;
+        }
+    };
+    for (part, model) in models.iter().enumerate() {
+        let location = perf.part(part as u8 + 1, || run_course(*model, init_loc, &commands));
+        print_part(part + 1, location);
+    }
+    let trajectories: Vec<Trajectory> = models
+        .iter()
+        .map(|model| Trajectory::record(*model, init_loc, &commands))
+        .collect();
+    for trajectory in &trajectories {
+        trajectory.print_events();
+    }
+    if let Some(path) = &cli.csv {
+        write_file(path, &trajectories, write_csv);
+    }
+    if let Some(path) = &cli.svg {
+        write_file(path, &trajectories, write_svg);
     }
 }
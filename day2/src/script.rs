@@ -0,0 +1,235 @@
+use crate::Command;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::iter;
+use std::rc::Rc;
+
+// Course scripts extend the plain format, so a plain course is already a valid
+// script:
+//
+//     # Comments run from `#` to the end of the line
+//     macro zigzag {
+//         down 3
+//         forward 2
+//         up 3
+//     }
+//     repeat 1000 {
+//         zigzag
+//         forward 1
+//     }
+//
+// Macros have to be defined at the top level before they are used, which also
+// rules out recursion. Each top level item is expanded as soon as it has been
+// parsed, so a plain course is still read one line at a time and repeats are
+// never held in memory.
+
+#[derive(Debug)]
+enum Op {
+    Command(Command),
+    Repeat(u32, Rc<[Op]>),
+    Call(Rc<[Op]>),
+}
+
+fn expand(op: &Op) -> Box<dyn Iterator<Item = Command>> {
+    match op {
+        Op::Command(command) => Box::new(iter::once(*command)),
+        Op::Repeat(count, body) => {
+            let body = body.clone();
+            Box::new((0..*count).flat_map(move |_| expand_all(body.clone())))
+        }
+        Op::Call(body) => expand_all(body.clone()),
+    }
+}
+
+fn expand_all(ops: Rc<[Op]>) -> Box<dyn Iterator<Item = Command>> {
+    Box::new((0..ops.len()).flat_map(move |i| expand(&ops[i])))
+}
+
+#[derive(Debug)]
+struct Token {
+    line: usize,
+    text: String,
+}
+
+/// Compiles a course script from `(line number, line)` pairs into the plain
+/// command stream. Errors name the line they were found on.
+pub(crate) struct Script<I> {
+    lines: I,
+    tokens: VecDeque<Token>,
+    macros: HashMap<String, (usize, Rc<[Op]>)>,
+    pending: Box<dyn Iterator<Item = Command>>,
+    failed: bool,
+}
+
+impl<I> Script<I>
+where
+    I: Iterator<Item = io::Result<(usize, String)>>,
+{
+    pub(crate) fn new(lines: I) -> Self {
+        Script {
+            lines,
+            tokens: VecDeque::new(),
+            macros: HashMap::new(),
+            pending: Box::new(iter::empty()),
+            failed: false,
+        }
+    }
+
+    // Reads lines until one has a word on it, with braces always standing on
+    // their own.
+    fn next_token(&mut self) -> io::Result<Option<Token>> {
+        while self.tokens.is_empty() {
+            let (line, text) = match self.lines.next() {
+                Some(line) => line?,
+                None => return Ok(None),
+            };
+            let code = text.split('#').next().unwrap_or_default();
+            let code = code.replace('{', " { ").replace('}', " } ");
+            self.tokens
+                .extend(code.split_whitespace().map(|word| Token {
+                    line,
+                    text: word.to_string(),
+                }));
+        }
+        Ok(self.tokens.pop_front())
+    }
+
+    // The next word, if it is on `line`. A line is always split in one go, so
+    // this never has to read any further.
+    fn peek_on(&self, line: usize) -> Option<&str> {
+        self.tokens
+            .front()
+            .filter(|token| token.line == line)
+            .map(|token| token.text.as_str())
+    }
+
+    // Takes the word that has to follow `keyword` on the same line.
+    fn argument(&mut self, line: usize, keyword: &str) -> io::Result<String> {
+        match self.peek_on(line) {
+            Some(word) if word != "{" && word != "}" => Ok(self.tokens.pop_front().unwrap().text),
+            _ => Err(error(line, format!("expected a value after `{}`", keyword))),
+        }
+    }
+
+    fn open(&mut self, line: usize, keyword: &str) -> io::Result<()> {
+        match self.peek_on(line) {
+            Some("{") => {
+                self.tokens.pop_front();
+                Ok(())
+            }
+            _ => Err(error(
+                line,
+                format!("expected `{{` to open the {}", keyword),
+            )),
+        }
+    }
+
+    // Parses the next item of a block opened on `open`, or of the top level.
+    // Macro definitions are stored rather than returned, and `None` means the
+    // block or the script has ended.
+    fn item(&mut self, open: Option<usize>) -> io::Result<Option<Op>> {
+        loop {
+            let Token { line, text } = match self.next_token()? {
+                Some(token) => token,
+                None => {
+                    return match open {
+                        Some(line) => Err(error(line, "`{` is never closed")),
+                        None => Ok(None),
+                    }
+                }
+            };
+            match text.as_str() {
+                "}" if open.is_some() => return Ok(None),
+                "}" => return Err(error(line, "`}` without a matching `{`")),
+                "{" => return Err(error(line, "`{` must follow `repeat N` or `macro NAME`")),
+                "repeat" => {
+                    let count = self.argument(line, &text)?;
+                    let count = count
+                        .parse::<u32>()
+                        .map_err(|e| error(line, format!("bad count `{}`: {}", count, e)))?;
+                    self.open(line, "repeat")?;
+                    return Ok(Some(Op::Repeat(count, self.block(line)?.into())));
+                }
+                "macro" => {
+                    if open.is_some() {
+                        return Err(error(line, "macros must be defined at the top level"));
+                    }
+                    let name = self.argument(line, &text)?;
+                    if is_reserved(&name) || name.parse::<i64>().is_ok() {
+                        return Err(error(line, format!("`{}` cannot name a macro", name)));
+                    }
+                    if let Some((defined, _)) = self.macros.get(&name) {
+                        return Err(error(
+                            line,
+                            format!("macro `{}` is already defined on line {}", name, defined),
+                        ));
+                    }
+                    self.open(line, "macro")?;
+                    let body = self.block(line)?;
+                    self.macros.insert(name, (line, body.into()));
+                }
+                "forward" | "down" | "up" => {
+                    let amount = self.argument(line, &text)?;
+                    let command = format!("{} {}", text, amount)
+                        .parse()
+                        .map_err(|e| error(line, e))?;
+                    return Ok(Some(Op::Command(command)));
+                }
+                name => {
+                    return match self.macros.get(name) {
+                        Some((_, body)) => Ok(Some(Op::Call(body.clone()))),
+                        None if self.peek_on(line).is_some() => {
+                            Err(error(line, format!("unknown command `{}`", name)))
+                        }
+                        None => Err(error(line, format!("unknown macro `{}`", name))),
+                    }
+                }
+            }
+        }
+    }
+
+    fn block(&mut self, open: usize) -> io::Result<Vec<Op>> {
+        let mut ops = Vec::new();
+        while let Some(op) = self.item(Some(open))? {
+            ops.push(op);
+        }
+        Ok(ops)
+    }
+}
+
+impl<I> Iterator for Script<I>
+where
+    I: Iterator<Item = io::Result<(usize, String)>>,
+{
+    type Item = io::Result<Command>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(command) = self.pending.next() {
+                return Some(Ok(command));
+            }
+            if self.failed {
+                return None;
+            }
+            match self.item(None) {
+                Ok(Some(op)) => self.pending = expand(&op),
+                Ok(None) => return None,
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+fn is_reserved(word: &str) -> bool {
+    matches!(word, "repeat" | "macro" | "forward" | "down" | "up")
+}
+
+fn error<S: ToString>(line: usize, message: S) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line, message.to_string()),
+    )
+}
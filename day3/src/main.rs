@@ -1,101 +1,582 @@
-use itertools::Itertools;
-use std::fs::File;
+use aoc::config::Config;
+use aoc::input::{open_input, Blanks, Lines, Normalize, NormalizeOpts};
+use aoc::perf::PerfOpts;
+use std::fmt;
 use std::io::{self, BufRead};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Day3", about = "Binary Diagnostic")]
 struct Cli {
-    /// Input file
+    /// Input file (or `-` for stdin), defaults to `input` in aoc.toml
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
+
+    /// Solve part 1 in a single pass without loading the whole input
+    #[structopt(long)]
+    stream: bool,
+
+    /// Base of the digits in the report, from 2 to 36
+    #[structopt(long, default_value = "2", parse(try_from_str = parse_radix))]
+    radix: u32,
+
+    /// Digit criterion for the oxygen rating, `most` or `least` common with
+    /// an optional `:DIGIT` that wins a tie
+    #[structopt(long, default_value = "most")]
+    oxygen: Rule,
+
+    /// Digit criterion for the CO2 rating, the same as `--oxygen`
+    #[structopt(long, default_value = "least")]
+    co2: Rule,
+
+    /// What a rating does when no candidates are left with the digit its
+    /// criterion picks: `fail`, or `other` to pick only among the digits that
+    /// still have candidates
+    #[structopt(long, default_value = "fail")]
+    on_exhausted: Exhaustion,
+
+    /// Show how many candidates survive each column of the ratings
+    #[structopt(long, conflicts_with = "stream")]
+    explain: bool,
+
+    #[structopt(flatten)]
+    normalize: NormalizeOpts,
+
+    #[structopt(flatten)]
+    perf: PerfOpts,
+}
+
+fn parse_radix(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>() {
+        Ok(radix) if (2..=36).contains(&radix) => Ok(radix),
+        _ => Err(format!("radix `{}` is not between 2 and 36", s)),
+    }
 }
 
 // The output is wrapped in a Result to allow matching on errors
-// Returns an Iterator to the Reader of the lines of the file.
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+// Returns an Iterator to the normalised lines of the file.
+fn read_lines<P>(
+    filename: P,
+    normalize: &NormalizeOpts,
+) -> io::Result<Normalize<Lines<Box<dyn BufRead>>>>
 where
     P: AsRef<Path>,
 {
-    let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    Ok(normalize.read(open_input(filename)?, Blanks::Drop))
+}
+
+fn invalid(line_no: usize, message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line_no, message),
+    )
+}
+
+fn no_readings() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "no readings")
+}
+
+// Reads the digits of one reading, which must have as many as the first one
+// and fit in a `u64`. Both `Report::parse` and `stream_power` go through this,
+// so they accept the same input.
+fn parse_digits(line: &str, radix: u32, width: &mut Option<usize>) -> Result<Vec<u32>, String> {
+    let digits = line
+        .chars()
+        .map(|c| {
+            c.to_digit(radix)
+                .ok_or_else(|| format!("`{}` is not a base {} digit", c, radix))
+        })
+        .collect::<Result<Vec<u32>, String>>()?;
+    let expected = *width.get_or_insert(digits.len());
+    if digits.len() != expected {
+        return Err(format!(
+            "reading has {} digits, expected {}",
+            digits.len(),
+            expected
+        ));
+    }
+    if digits.len() > max_width(radix) as usize {
+        return Err(format!(
+            "reading has {} digits, at most {} fit",
+            digits.len(),
+            max_width(radix)
+        ));
+    }
+    Ok(digits)
+}
+
+/// A diagnostic report with every reading packed into a `u64` as a number in
+/// `radix`, so the first column of the input is the most significant digit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Report {
+    radix: u32,
+    width: u32,
+    readings: Vec<u64>,
+}
+
+impl Report {
+    fn parse<I>(mut lines: Normalize<I>, radix: u32) -> io::Result<Report>
+    where
+        I: Iterator<Item = io::Result<String>>,
+    {
+        let mut width = None;
+        let mut readings = Vec::new();
+        while let Some(line) = lines.next() {
+            let digits = parse_digits(&line?, radix, &mut width)
+                .map_err(|message| invalid(lines.line_no(), message))?;
+            readings.push(
+                digits
+                    .into_iter()
+                    .fold(0, |reading, digit| reading * radix as u64 + digit as u64),
+            );
+        }
+        match width {
+            Some(width) => Ok(Report {
+                radix,
+                width: width as u32,
+                readings,
+            }),
+            None => Err(no_readings()),
+        }
+    }
+
+    // How many readings have each digit, for every column from the left.
+    fn columns(&self) -> Vec<Vec<usize>> {
+        (0..self.width)
+            .map(|column| {
+                let place = place(self.radix, self.width - 1 - column);
+                let mut counts = vec![0; self.radix as usize];
+                for reading in &self.readings {
+                    counts[digit(*reading, place, self.radix)] += 1;
+                }
+                counts
+            })
+            .collect()
+    }
+}
+
+// The largest width whose readings all fit in a `u64`.
+fn max_width(radix: u32) -> u32 {
+    let mut width = 0;
+    let mut size: u128 = radix as u128;
+    while size <= 1 << 64 {
+        size *= radix as u128;
+        width += 1;
+    }
+    width
+}
+
+// The value of a 1 in the `power` place, counting from 0 on the right.
+fn place(radix: u32, power: u32) -> u64 {
+    (radix as u64).pow(power)
+}
+
+fn digit(reading: u64, place: u64, radix: u32) -> usize {
+    (reading / place % radix as u64) as usize
+}
+
+// Builds a number from the digit `rule` picks in every column.
+fn rate(columns: &[Vec<usize>], radix: u32, rule: Rule) -> u64 {
+    columns.iter().fold(0, |rate, counts| {
+        rate * radix as u64 + rule.choose(counts, false) as u64
+    })
+}
+
+// Writes `value` in `radix`, padded with zeros to `width` digits.
+fn to_radix(mut value: u128, radix: u32, width: usize) -> String {
+    let mut digits = Vec::new();
+    while value > 0 || digits.len() < width.max(1) {
+        digits.push(std::char::from_digit((value % radix as u128) as u32, radix).unwrap());
+        value /= radix as u128;
+    }
+    digits.iter().rev().collect()
 }
 
-fn common_for_column(it: &Vec<String>, col: usize) -> char {
-    let data = it.into_iter().map(|s| s.chars().nth(col).unwrap()).counts();
-    if data.get(&'0').unwrap_or(&0) > data.get(&'1').unwrap_or(&0) {
-        '0'
+// Prints a value in decimal, and in the source radix unless that is decimal.
+fn print_value(label: &str, value: u128, radix: u32, width: usize) {
+    if radix == 10 {
+        println!("{}: {}", label, value);
     } else {
-        '1'
+        let digits = to_radix(value, radix, width);
+        println!("{}: {} ({} in base {})", label, value, digits, radix);
     }
 }
 
-fn calculate_gamma(it: &Vec<String>) -> String {
-    (0..it[0].len())
-        .map(|col| common_for_column(it, col))
-        .collect()
+/// A trie of the readings, most significant digit first, where each node
+/// counts the readings below it. A rating is a single walk from the root.
+#[derive(Debug, Clone)]
+struct Trie {
+    radix: u32,
+    width: u32,
+    counts: Vec<usize>,
+    // The children of node `n` are at `n * radix..(n + 1) * radix`, where 0
+    // (the root) means no child.
+    children: Vec<usize>,
 }
 
-fn calculate_epsilon(it: &Vec<String>) -> String {
-    (0..it[0].len())
-        .map(|col| {
-            if common_for_column(it, col) == '0' {
-                '1'
+impl Trie {
+    fn build(report: &Report) -> Trie {
+        let radix = report.radix as usize;
+        let mut counts = vec![0];
+        let mut children = vec![0; radix];
+        for reading in &report.readings {
+            let mut node = 0;
+            counts[node] += 1;
+            for power in (0..report.width).rev() {
+                let slot = node * radix + digit(*reading, place(report.radix, power), report.radix);
+                if children[slot] == 0 {
+                    children[slot] = counts.len();
+                    counts.push(0);
+                    children.resize(children.len() + radix, 0);
+                }
+                node = children[slot];
+                counts[node] += 1;
+            }
+        }
+        Trie {
+            radix: report.radix,
+            width: report.width,
+            counts,
+            children,
+        }
+    }
+
+    fn child_counts(&self, node: usize) -> Vec<usize> {
+        let radix = self.radix as usize;
+        self.children[node * radix..(node + 1) * radix]
+            .iter()
+            .map(|child| if *child == 0 { 0 } else { self.counts[*child] })
+            .collect()
+    }
+
+    /// Finds the rating picked by `criterion`, which is given how many
+    /// candidates have each digit in a column and returns the digit to keep.
+    /// Once one candidate is left the rest of it is read off without asking.
+    /// Each decision is appended to `trace`.
+    fn rating<F>(&self, mut criterion: F, trace: &mut Vec<Step>) -> Result<u64, RatingError>
+    where
+        F: FnMut(&[usize]) -> usize,
+    {
+        let mut node = 0;
+        let mut value = 0;
+        for column in 1..=self.width {
+            let counts = self.child_counts(node);
+            let digit = if self.counts[node] == 1 {
+                counts.iter().position(|count| *count > 0).unwrap()
             } else {
-                '0'
+                criterion(&counts)
+            };
+            let child = self.children[node * self.radix as usize + digit];
+            if self.counts[node] > 1 {
+                trace.push(Step {
+                    column,
+                    left: counts[digit],
+                    counts,
+                    kept: digit as u32,
+                });
             }
-        })
-        .collect()
-}
-
-fn find_oxy(it: &Vec<String>) -> String {
-    fn find_oxy_part(it: &Vec<String>, col: usize) -> String {
-        let common = common_for_column(it, col);
-        let new_it: Vec<String> = it
-            .into_iter()
-            .cloned()
-            .filter(|s| s.chars().nth(col).unwrap() == common)
-            .collect();
-        if new_it.len() <= 1 {
-            new_it[0].clone()
-        } else {
-            find_oxy_part(&new_it, col + 1)
+            if child == 0 {
+                return Err(RatingError::Exhausted {
+                    column,
+                    kept: digit as u32,
+                    radix: self.radix,
+                });
+            }
+            node = child;
+            value = value * self.radix as u64 + digit as u64;
         }
+        Ok(value)
     }
-    find_oxy_part(it, 0)
-}
-
-fn find_co2(it: &Vec<String>) -> String {
-    fn find_co2_part(it: &Vec<String>, col: usize) -> String {
-        let common = common_for_column(it, col);
-        let new_it: Vec<String> = it
-            .into_iter()
-            .cloned()
-            .filter(|s| s.chars().nth(col).unwrap() != common)
-            .collect();
-        if new_it.len() <= 1 {
-            new_it[0].clone()
-        } else {
-            find_co2_part(&new_it, col + 1)
+}
+
+/// One decision of a rating search, for `--explain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Step {
+    column: u32,
+    counts: Vec<usize>,
+    kept: u32,
+    left: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RatingError {
+    /// The criterion kept the readings with `kept` in `column` (counting
+    /// from 1 on the left), but none of the candidates had it.
+    Exhausted { column: u32, kept: u32, radix: u32 },
+}
+
+impl fmt::Display for RatingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RatingError::Exhausted {
+                column,
+                kept,
+                radix,
+            } => write!(
+                f,
+                "no candidates are left with a {} in column {}",
+                std::char::from_digit(*kept, *radix).unwrap(),
+                column
+            ),
+        }
+    }
+}
+
+/// What a rating search does when the criterion picks a digit that no
+/// candidate has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exhaustion {
+    /// Stop with an error
+    Fail,
+    /// Pick again among the digits that still have candidates, which for
+    /// binary is the other side
+    Other,
+}
+
+impl FromStr for Exhaustion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(Exhaustion::Fail),
+            "other" => Ok(Exhaustion::Other),
+            _ => Err(format!("unknown exhaustion behaviour `{}`", s)),
         }
     }
-    find_co2_part(it, 0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Criterion {
+    MostCommon,
+    LeastCommon,
+}
+
+/// A digit criterion, written `most` or `least` with an optional `:DIGIT`.
+///
+/// Every digit of the radix takes part, so a digit no candidate has is the
+/// least common one. When several digits tie, the one nearest to the tie digit
+/// wins, and the higher one if two are equally near. The tie digit defaults to
+/// the highest digit for `most` and to 0 for `least`, which in binary is the
+/// usual ties to 1 for gamma and oxygen and to 0 for epsilon and CO2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rule {
+    criterion: Criterion,
+    tie: Option<u32>,
+}
+
+const GAMMA: Rule = Rule {
+    criterion: Criterion::MostCommon,
+    tie: None,
+};
+
+const EPSILON: Rule = Rule {
+    criterion: Criterion::LeastCommon,
+    tie: None,
+};
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (criterion, tie) = match s.split_once(':') {
+            Some((criterion, tie)) => (criterion, Some(tie)),
+            None => (s, None),
+        };
+        let criterion = match criterion {
+            "most" => Criterion::MostCommon,
+            "least" => Criterion::LeastCommon,
+            _ => return Err(format!("unknown criterion `{}`", criterion)),
+        };
+        let tie = match tie {
+            None => None,
+            Some(tie) => {
+                let mut chars = tie.chars();
+                match (chars.next().and_then(|c| c.to_digit(36)), chars.next()) {
+                    (Some(digit), None) => Some(digit),
+                    _ => return Err(format!("tie-break `{}` is not a single digit", tie)),
+                }
+            }
+        };
+        Ok(Rule { criterion, tie })
+    }
+}
+
+impl Rule {
+    fn check(&self, radix: u32) -> Result<(), String> {
+        match self.tie {
+            Some(tie) if tie >= radix => Err(format!(
+                "tie-break {} is not a base {} digit",
+                std::char::from_digit(tie, 36).unwrap(),
+                radix
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn describe(&self, radix: u32) -> String {
+        let criterion = match self.criterion {
+            Criterion::MostCommon => "most",
+            Criterion::LeastCommon => "least",
+        };
+        let tie = std::char::from_digit(self.tie_digit(radix as usize) as u32, radix).unwrap();
+        format!("{} common digit, ties to {}", criterion, tie)
+    }
+
+    fn tie_digit(&self, radix: usize) -> usize {
+        match (self.tie, self.criterion) {
+            (Some(tie), _) => tie as usize,
+            (None, Criterion::MostCommon) => radix - 1,
+            (None, Criterion::LeastCommon) => 0,
+        }
+    }
+
+    // Picks a digit from how many candidates have each one, optionally
+    // ignoring the digits no candidate has.
+    fn choose(&self, counts: &[usize], present_only: bool) -> usize {
+        let digits = (0..counts.len()).filter(|d| !present_only || counts[*d] > 0);
+        let best = match self.criterion {
+            Criterion::MostCommon => digits.clone().map(|d| counts[d]).max(),
+            Criterion::LeastCommon => digits.clone().map(|d| counts[d]).min(),
+        };
+        let tie = self.tie_digit(counts.len());
+        digits
+            .filter(|d| Some(counts[*d]) == best)
+            .min_by_key(|d| (d.abs_diff(tie), std::cmp::Reverse(*d)))
+            .unwrap_or(tie)
+    }
+
+    fn rating(
+        &self,
+        trie: &Trie,
+        on_exhausted: Exhaustion,
+        trace: &mut Vec<Step>,
+    ) -> Result<u64, RatingError> {
+        let present_only = on_exhausted == Exhaustion::Other;
+        trie.rating(|counts| self.choose(counts, present_only), trace)
+    }
+}
+
+fn print_explanation(name: &str, rule: Rule, radix: u32, trace: &[Step]) {
+    println!("{} rating, {}:", name, rule.describe(radix));
+    for step in trace {
+        let counts = step
+            .counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(digit, count)| {
+                format!(
+                    "{}={}",
+                    std::char::from_digit(digit as u32, radix).unwrap(),
+                    count
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        println!(
+            "  column {}: {}, kept {}, {} left",
+            step.column,
+            counts,
+            std::char::from_digit(step.kept, radix).unwrap(),
+            step.left
+        );
+    }
+}
+
+// Tallies the digits in each column as the readings arrive, then derives
+// gamma and epsilon from the tallies.
+fn stream_power<I>(mut lines: Normalize<I>, radix: u32) -> io::Result<(u64, u64, usize)>
+where
+    I: Iterator<Item = io::Result<String>>,
+{
+    let mut width = None;
+    let mut columns: Vec<Vec<usize>> = Vec::new();
+    while let Some(line) = lines.next() {
+        let digits = parse_digits(&line?, radix, &mut width)
+            .map_err(|message| invalid(lines.line_no(), message))?;
+        if columns.is_empty() {
+            columns = vec![vec![0; radix as usize]; digits.len()];
+        }
+        for (col, digit) in digits.into_iter().enumerate() {
+            columns[col][digit as usize] += 1;
+        }
+    }
+    if width.is_none() {
+        return Err(no_readings());
+    }
+    // The same rules as `Report`, so the answers match
+    Ok((
+        rate(&columns, radix, GAMMA),
+        rate(&columns, radix, EPSILON),
+        columns.len(),
+    ))
 }
 
 fn main() {
     let cli = Cli::from_args();
-    let file_lines = read_lines(cli.input);
-    if let Ok(lines) = file_lines {
-        let data: Vec<_> = lines.filter_map(Result::ok).collect();
-
-        let gamma = i32::from_str_radix(&calculate_gamma(&data), 2).unwrap();
-        let epsilon = i32::from_str_radix(&calculate_epsilon(&data), 2).unwrap();
+    let config = Config::for_day(3);
+    let perf = cli.perf.recorder(&config);
+    let radix = cli.radix;
+    for rule in [cli.oxygen, cli.co2] {
+        if let Err(e) = rule.check(radix) {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    }
+    let input = config.input(cli.input.clone());
+    if cli.stream {
+        let power = perf.part(1, || {
+            read_lines(input, &cli.normalize).and_then(|l| stream_power(l, radix))
+        });
+        match power {
+            Ok((gamma, epsilon, width)) => {
+                print_value("Gamma rate", gamma as u128, radix, width);
+                print_value("Epsilon rate", epsilon as u128, radix, width);
+                print_value("Part 1", gamma as u128 * epsilon as u128, radix, 0);
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+        return;
+    }
+    match read_lines(input, &cli.normalize).and_then(|l| Report::parse(l, radix)) {
+        Ok(report) => {
+            let (gamma, epsilon) = perf.part(1, || {
+                let columns = report.columns();
+                (rate(&columns, radix, GAMMA), rate(&columns, radix, EPSILON))
+            });
 
-        let oxygen = i32::from_str_radix(&find_oxy(&data), 2).unwrap();
-        let co2 = i32::from_str_radix(&find_co2(&data), 2).unwrap();
+            let (oxygen_rule, co2_rule) = (cli.oxygen, cli.co2);
+            let (mut oxygen_trace, mut co2_trace) = (Vec::new(), Vec::new());
+            let ratings = perf.part(2, || {
+                let trie = Trie::build(&report);
+                let oxygen = oxygen_rule
+                    .rating(&trie, cli.on_exhausted, &mut oxygen_trace)
+                    .map_err(|e| ("oxygen", e))?;
+                let co2 = co2_rule
+                    .rating(&trie, cli.on_exhausted, &mut co2_trace)
+                    .map_err(|e| ("CO2", e))?;
+                Ok((oxygen, co2))
+            });
 
-        println!("Part 1: {:?}", gamma * epsilon);
-        println!("Part 2: {:?}", oxygen * co2);
+            if cli.explain {
+                print_explanation("Oxygen", oxygen_rule, radix, &oxygen_trace);
+                print_explanation("CO2", co2_rule, radix, &co2_trace);
+            }
+            let width = report.width as usize;
+            print_value("Gamma rate", gamma as u128, radix, width);
+            print_value("Epsilon rate", epsilon as u128, radix, width);
+            print_value("Part 1", gamma as u128 * epsilon as u128, radix, 0);
+            match ratings {
+                Ok((oxygen, co2)) => {
+                    print_value("Oxygen rating", oxygen as u128, radix, width);
+                    print_value("CO2 rating", co2 as u128, radix, width);
+                    print_value("Part 2", oxygen as u128 * co2 as u128, radix, 0);
+                }
+                Err((name, e)) => println!("Part 2: no {} rating, {}", name, e),
+            }
+        }
+        Err(e) => eprintln!("{}", e),
     }
 }
@@ -0,0 +1,124 @@
+use crate::{Engine, Index, Win};
+use std::collections::HashSet;
+use std::io::{self, BufRead};
+
+// At most this many boards are listed as near misses after each call.
+const NEAR_MISSES: usize = 10;
+
+fn print_win(win: &Win) {
+    println!(
+        "  Board {} wins with {}, score {}",
+        win.board + 1,
+        win.line,
+        win.score
+    );
+}
+
+// The board still in play that needs the fewest numbers to win, with the
+// lowest index breaking ties.
+fn print_leader(engine: &Engine, boards: usize) {
+    let mut leaders = (0..boards)
+        .filter(|board| !engine.has_won(*board))
+        .filter_map(|board| engine.to_win(board).map(|needed| (needed, board)))
+        .collect::<Vec<_>>();
+    leaders.sort_unstable();
+    match leaders.first() {
+        Some((needed, board)) => {
+            let tied = leaders.iter().filter(|(n, _)| n == needed).count() - 1;
+            println!(
+                "  Leader: board {}, {} number{} from winning{}",
+                board + 1,
+                needed,
+                if *needed == 1 { "" } else { "s" },
+                match tied {
+                    0 => String::new(),
+                    1 => ", tied with 1 other".to_string(),
+                    tied => format!(", tied with {} others", tied),
+                }
+            );
+        }
+        None if (0..boards).all(|board| engine.has_won(board)) => {
+            println!("  Leader: every board has won")
+        }
+        None => println!("  Leader: none, no board left in play can win"),
+    }
+}
+
+fn print_near_misses(engine: &Engine, boards: usize) {
+    let misses: Vec<String> = (0..boards)
+        .filter_map(|board| {
+            let needs: Vec<String> = engine
+                .one_away(board)
+                .iter()
+                .map(|(number, line)| format!("{} ({})", number, line))
+                .collect();
+            if needs.is_empty() {
+                None
+            } else {
+                Some(format!("board {} needs {}", board + 1, needs.join(" or ")))
+            }
+        })
+        .collect();
+    if misses.is_empty() {
+        return;
+    }
+    let more = misses.len().saturating_sub(NEAR_MISSES);
+    let mut shown = misses[..misses.len() - more].join(", ");
+    if more > 0 {
+        shown += &format!(" and {} more", more);
+    }
+    println!("  Near misses: {}", shown);
+}
+
+// Calls numbers as they arrive on `calls`, one or more to a line separated by
+// commas or spaces, and announces what each one does to the boards. Numbers
+// that were already called, or that are not numbers, are reported and
+// skipped.
+pub(crate) fn run<R: BufRead>(index: &Index, calls: R) -> io::Result<()> {
+    let boards = index.boards.len();
+    let mut engine = Engine::new(index);
+    let mut called = HashSet::new();
+    let mut winners = Vec::new();
+    println!("{} boards loaded, waiting for numbers", boards);
+    for line in calls.lines() {
+        let line = line?;
+        for word in line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|word| !word.is_empty())
+        {
+            let number = match word.parse::<u32>() {
+                Ok(number) => number,
+                Err(_) => {
+                    println!("`{}` is not a number", word);
+                    continue;
+                }
+            };
+            if !called.insert(number) {
+                println!("{} was already called", number);
+                continue;
+            }
+            println!("Call {}: {}", called.len(), number);
+            let wins = engine.draw(number);
+            wins.iter().for_each(print_win);
+            winners.extend(wins);
+            print_leader(&engine, boards);
+            print_near_misses(&engine, boards);
+        }
+    }
+    println!(
+        "{} numbers called, {} of {} boards won",
+        called.len(),
+        winners.len(),
+        boards
+    );
+    if let (Some(first), Some(last)) = (winners.first(), winners.last()) {
+        println!(
+            "First: board {}, score {}; last: board {}, score {}",
+            first.board + 1,
+            first.score,
+            last.board + 1,
+            last.score
+        );
+    }
+    Ok(())
+}
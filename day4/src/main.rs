@@ -1,98 +1,872 @@
-use itertools::Itertools;
-use std::collections::BTreeSet;
+use aoc::config::Config;
+use aoc::input::{Blanks, Lines, Normalize, NormalizeOpts};
+use aoc::perf::PerfOpts;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
-use std::io::{self, BufRead};
-use std::iter::Iterator;
+use std::io;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use structopt::StructOpt;
 
-const BINGO_SIZE: usize = 5;
+mod live;
+mod rig;
+use rig::Goal;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Day4", about = "Giant Squid")]
 struct Cli {
-    /// Input file
+    /// Input file, defaults to `input` in aoc.toml
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
+
+    /// Require every board to be `ROWSxCOLS` instead of taking each board's
+    /// shape from its rows
+    #[structopt(long)]
+    size: Option<Shape>,
+
+    /// Win rule: diagonals, corners, blackout, free-centre or lines:N, and
+    /// may be given more than once
+    #[structopt(long, number_of_values = 1)]
+    rule: Vec<Rule>,
+
+    /// Estimate each board's chances of winning first or last, and when it
+    /// wins, over this many random orders of the draw numbers
+    #[structopt(long, parse(try_from_str = parse_runs))]
+    simulate: Option<u64>,
+
+    /// Seed for the random draw orders of `--simulate`
+    #[structopt(long, default_value = "0")]
+    seed: u64,
+
+    /// Find the shortest draw order, from the numbers on the first line,
+    /// that makes this board win first
+    #[structopt(long)]
+    rig: Option<usize>,
+
+    /// Rig the draw so the board given to `--rig` wins last instead
+    #[structopt(long, requires = "rig")]
+    last: bool,
+
+    /// Call numbers read from stdin one at a time, announcing winners, the
+    /// leader and near misses. The input needs no draws line
+    #[structopt(long, conflicts_with_all = &["rig", "simulate", "ranking"])]
+    live: bool,
+
+    /// Print every board in the order it wins
+    #[structopt(long)]
+    ranking: bool,
+
+    #[structopt(flatten)]
+    normalize: NormalizeOpts,
+
+    #[structopt(flatten)]
+    perf: PerfOpts,
+}
+
+fn parse_runs(s: &str) -> Result<u64, String> {
+    match s.parse::<u64>() {
+        Ok(runs) if runs > 0 => Ok(runs),
+        _ => Err(format!("`{}` is not a number of runs of at least 1", s)),
+    }
 }
 
 // The output is wrapped in a Result to allow matching on errors
-// Returns an Iterator to the Reader of the lines of the file.
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+// Returns an Iterator to the normalised lines of the file.
+fn read_lines<P>(
+    filename: P,
+    normalize: &NormalizeOpts,
+) -> io::Result<Normalize<Lines<io::BufReader<File>>>>
 where
     P: AsRef<Path>,
 {
     let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    Ok(normalize.read(io::BufReader::new(file), Blanks::Collapse))
+}
+
+/// The number of rows and columns of a board, written `ROWSxCOLS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Shape {
+    rows: usize,
+    cols: usize,
 }
 
-fn get_column(board: &[[u32; BINGO_SIZE]; BINGO_SIZE], nth: usize) -> [u32; BINGO_SIZE] {
-    let mut col = [0; BINGO_SIZE];
-    for (i, row) in board.iter().enumerate() {
-        col[i] = row[nth];
+impl FromStr for Shape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rows, cols) = s
+            .split_once('x')
+            .ok_or_else(|| format!("expected ROWSxCOLS, got `{}`", s))?;
+        let parse = |n: &str| match n.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("bad board dimension `{}`", n)),
+        };
+        Ok(Shape {
+            rows: parse(rows)?,
+            cols: parse(cols)?,
+        })
     }
-    col
 }
 
-fn score_board(board: &[[u32; BINGO_SIZE]; BINGO_SIZE], set: &BTreeSet<u32>) -> u32 {
-    board.iter().flatten().filter(|i| !set.contains(i)).sum()
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}", self.rows, self.cols)
+    }
+}
+
+/// A bingo board of any shape, with its numbers stored row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Board {
+    shape: Shape,
+    cells: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Game {
+    draws: Vec<u32>,
+    boards: Vec<Board>,
+}
+
+fn invalid<S: ToString>(line_no: usize, message: S) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line_no, message.to_string()),
+    )
+}
+
+fn parse_numbers<T: FromStr>(line_no: usize, numbers: &[&str]) -> io::Result<Vec<T>> {
+    numbers
+        .iter()
+        .map(|n| {
+            n.parse()
+                .map_err(|_| invalid(line_no, format!("`{}` is not a number", n)))
+        })
+        .collect()
+}
+
+// Reads the draws from the first line and then the boards.
+fn parse_game<I>(mut lines: Normalize<I>, shape: Option<Shape>) -> io::Result<Game>
+where
+    I: Iterator<Item = io::Result<String>>,
+{
+    let draws = match lines.next() {
+        Some(line) => {
+            let line = line?;
+            let numbers: Vec<&str> = line.split(',').map(str::trim).collect();
+            parse_numbers(lines.line_no(), &numbers)?
+        }
+        None => return Err(invalid(1, "no draws")),
+    };
+    let boards = parse_boards(&mut lines, None, shape)?;
+    Ok(Game { draws, boards })
 }
 
-fn find_winning_board(
-    boards: &Vec<[[u32; BINGO_SIZE]; BINGO_SIZE]>,
-    results: &Vec<u32>,
-) -> (usize, u32) {
-    let mut result_set: BTreeSet<u32> = BTreeSet::new();
+// Reads the boards for live mode, skipping the draws line if the input has
+// one, since the draws come from stdin instead.
+fn parse_live_boards<I>(mut lines: Normalize<I>, shape: Option<Shape>) -> io::Result<Vec<Board>>
+where
+    I: Iterator<Item = io::Result<String>>,
+{
+    let first = lines.next().transpose()?.filter(|line| !line.contains(','));
+    parse_boards(&mut lines, first, shape)
+}
 
-    for result_pos in 0..results.len() {
-        result_set.insert(results[result_pos]);
-        for (board_number, board) in boards.iter().enumerate() {
-            for row in board {
-                if row.iter().all(|i| result_set.contains(i)) {
-                    let score = score_board(&board, &result_set);
-                    return (board_number, score * results[result_pos]);
+// Reads boards separated by blank lines until the input runs out, starting
+// with `first` if a line has already been read. Each board takes its shape
+// from its own rows unless `shape` is given.
+fn parse_boards<I>(
+    lines: &mut Normalize<I>,
+    mut first: Option<String>,
+    shape: Option<Shape>,
+) -> io::Result<Vec<Board>>
+where
+    I: Iterator<Item = io::Result<String>>,
+{
+    let mut boards = Vec::new();
+    let mut rows: Vec<Vec<u32>> = Vec::new();
+    let mut first_line = 0;
+    loop {
+        let line = match first.take() {
+            Some(line) => Some(line),
+            None => lines.next().transpose()?,
+        };
+        let line_no = lines.line_no();
+        match line.as_deref() {
+            Some("") | None => {
+                if !rows.is_empty() {
+                    boards.push(finish_board(&rows, shape, boards.len(), first_line)?);
+                    rows.clear();
+                }
+                if line.is_none() {
+                    break;
                 }
             }
-            for col_num in 0..BINGO_SIZE {
-                let col = get_column(&board, col_num);
-                if col.iter().all(|i| result_set.contains(i)) {
-                    let score = score_board(&board, &result_set);
-                    return (board_number, score * results[result_pos]);
+            Some(line) => {
+                let numbers: Vec<&str> = line.split_whitespace().collect();
+                let row: Vec<u32> = parse_numbers(line_no, &numbers)?;
+                let cols = match (shape, rows.first()) {
+                    (Some(shape), _) => shape.cols,
+                    (None, Some(first)) => first.len(),
+                    (None, None) => row.len(),
+                };
+                if row.len() != cols {
+                    return Err(invalid(
+                        line_no,
+                        format!("row has {} numbers, expected {}", row.len(), cols),
+                    ));
+                }
+                if rows.is_empty() {
+                    first_line = line_no;
+                }
+                let repeated = row
+                    .iter()
+                    .enumerate()
+                    .find(|(i, n)| row[..*i].contains(n) || rows.iter().any(|r| r.contains(n)));
+                if let Some((_, n)) = repeated {
+                    return Err(invalid(
+                        line_no,
+                        format!("{} appears twice on board {}", n, boards.len() + 1),
+                    ));
                 }
+                rows.push(row);
             }
         }
     }
-    return (0, 0);
+    if boards.is_empty() {
+        return Err(invalid(lines.line_no(), "no boards"));
+    }
+    Ok(boards)
 }
 
-fn main() {
-    let cli = Cli::from_args();
-    let file_lines = read_lines(cli.input);
-    if let Ok(lines) = file_lines {
-        let data: Vec<_> = lines.filter_map(Result::ok).collect();
-        let results: Vec<u32> = data[0]
-            .split(&",")
-            .map(|s| s.parse::<u32>().unwrap())
+fn finish_board(
+    rows: &[Vec<u32>],
+    shape: Option<Shape>,
+    index: usize,
+    first_line: usize,
+) -> io::Result<Board> {
+    let shape = match shape {
+        Some(shape) if rows.len() != shape.rows => {
+            return Err(invalid(
+                first_line,
+                format!(
+                    "board {} has {} rows, expected {}",
+                    index + 1,
+                    rows.len(),
+                    shape.rows
+                ),
+            ))
+        }
+        Some(shape) => shape,
+        None => Shape {
+            rows: rows.len(),
+            cols: rows[0].len(),
+        },
+    };
+    Ok(Board {
+        shape,
+        cells: rows.concat(),
+    })
+}
+
+/// A win rule, given to `--rule` once or more. With none, any complete row
+/// or column wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rule {
+    /// The two diagonals of a square board count as lines
+    Diagonals,
+    /// Marking all four corners counts as a line
+    Corners,
+    /// Only marking every cell wins
+    Blackout,
+    /// The centre cell of a board with odd sides starts marked
+    FreeCentre,
+    /// A board needs this many lines to win
+    Lines(usize),
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "diagonals" => Ok(Rule::Diagonals),
+            "corners" => Ok(Rule::Corners),
+            "blackout" => Ok(Rule::Blackout),
+            "free-centre" | "free-center" => Ok(Rule::FreeCentre),
+            _ => match s.strip_prefix("lines:").map(str::parse) {
+                Some(Ok(n)) if n > 0 => Ok(Rule::Lines(n)),
+                _ => Err(format!(
+                    "unknown rule `{}`, expected diagonals, corners, blackout, free-centre or lines:N",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// Every rule in play, combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rules {
+    diagonals: bool,
+    corners: bool,
+    blackout: bool,
+    free_centre: bool,
+    lines: usize,
+}
+
+impl Rules {
+    fn new(rules: &[Rule]) -> Rules {
+        let mut combined = Rules {
+            diagonals: false,
+            corners: false,
+            blackout: false,
+            free_centre: false,
+            lines: 1,
+        };
+        for rule in rules {
+            match rule {
+                Rule::Diagonals => combined.diagonals = true,
+                Rule::Corners => combined.corners = true,
+                Rule::Blackout => combined.blackout = true,
+                Rule::FreeCentre => combined.free_centre = true,
+                Rule::Lines(n) => combined.lines = *n,
+            }
+        }
+        combined
+    }
+}
+
+/// The line that completed a board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line {
+    Row(usize),
+    Column(usize),
+    Diagonal,
+    AntiDiagonal,
+    Corners,
+    FullCard,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Line::Row(nth) => write!(f, "row {}", nth + 1),
+            Line::Column(nth) => write!(f, "column {}", nth + 1),
+            Line::Diagonal => write!(f, "diagonal"),
+            Line::AntiDiagonal => write!(f, "anti-diagonal"),
+            Line::Corners => write!(f, "corners"),
+            Line::FullCard => write!(f, "full card"),
+        }
+    }
+}
+
+/// The lines a board of one shape can complete under the rules, and which of
+/// them pass through each cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Layout {
+    lines: Vec<(Line, Vec<usize>)>,
+    lines_through: Vec<Vec<usize>>,
+    free: Option<usize>,
+}
+
+impl Layout {
+    fn new(shape: Shape, rules: &Rules) -> Layout {
+        let Shape { rows, cols } = shape;
+        let mut lines = Vec::new();
+        if rules.blackout {
+            lines.push((Line::FullCard, (0..rows * cols).collect()));
+        } else {
+            for row in 0..rows {
+                lines.push((Line::Row(row), (0..cols).map(|c| row * cols + c).collect()));
+            }
+            for col in 0..cols {
+                lines.push((
+                    Line::Column(col),
+                    (0..rows).map(|r| r * cols + col).collect(),
+                ));
+            }
+            if rules.diagonals && rows == cols {
+                lines.push((Line::Diagonal, (0..rows).map(|i| i * cols + i).collect()));
+                lines.push((
+                    Line::AntiDiagonal,
+                    (0..rows).map(|i| i * cols + cols - 1 - i).collect(),
+                ));
+            }
+            if rules.corners {
+                let mut corners = vec![0, cols - 1, (rows - 1) * cols, rows * cols - 1];
+                corners.sort_unstable();
+                corners.dedup();
+                lines.push((Line::Corners, corners));
+            }
+        }
+        let mut lines_through = vec![Vec::new(); rows * cols];
+        for (index, (_, cells)) in lines.iter().enumerate() {
+            for cell in cells {
+                lines_through[*cell].push(index);
+            }
+        }
+        let free = if rules.free_centre && rows % 2 == 1 && cols % 2 == 1 {
+            Some(rows / 2 * cols + cols / 2)
+        } else {
+            None
+        };
+        Layout {
+            lines,
+            lines_through,
+            free,
+        }
+    }
+}
+
+/// When and how a board won. `turn` counts draws from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Win {
+    board: usize,
+    turn: usize,
+    number: u32,
+    line: Line,
+    score: u64,
+}
+
+/// The order the boards finish in, with the boards that never win.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Ranking {
+    wins: Vec<Win>,
+    never: Vec<usize>,
+}
+
+/// The marks on one board, how far along each of its lines is and the sum of
+/// its unmarked numbers, all kept up to date as cells are marked.
+#[derive(Debug, Clone)]
+struct Progress {
+    marked: Vec<bool>,
+    hits: Vec<usize>,
+    complete: usize,
+    unmarked: u64,
+}
+
+impl Progress {
+    fn new(board: &Board, layout: &Layout) -> Progress {
+        let mut progress = Progress {
+            marked: vec![false; layout.lines_through.len()],
+            hits: vec![0; layout.lines.len()],
+            complete: 0,
+            unmarked: board.cells.iter().map(|n| *n as u64).sum(),
+        };
+        if let Some(cell) = layout.free {
+            progress.mark(board, layout, cell);
+        }
+        progress
+    }
+
+    // Marks a cell, returning the last line it completed.
+    fn mark(&mut self, board: &Board, layout: &Layout, cell: usize) -> Option<Line> {
+        if self.marked[cell] {
+            return None;
+        }
+        self.marked[cell] = true;
+        self.unmarked -= board.cells[cell] as u64;
+        let mut completed = None;
+        for line in &layout.lines_through[cell] {
+            self.hits[*line] += 1;
+            if self.hits[*line] == layout.lines[*line].1.len() {
+                self.complete += 1;
+                completed = Some(layout.lines[*line].0);
+            }
+        }
+        completed
+    }
+}
+
+/// Everything about the boards that does not change during a game: the
+/// layout of each board and an index from each number to the cells it is on,
+/// so a draw only touches the boards that have it.
+#[derive(Debug, Clone)]
+struct Index<'a> {
+    boards: &'a [Board],
+    rules: Rules,
+    layouts: Vec<Layout>,
+    layout_of: Vec<usize>,
+    cells_of: HashMap<u32, Vec<(usize, usize)>>,
+    start: Vec<Progress>,
+}
+
+impl<'a> Index<'a> {
+    fn new(boards: &'a [Board], rules: &Rules) -> Index<'a> {
+        let mut shapes: HashMap<Shape, usize> = HashMap::new();
+        let mut layouts = Vec::new();
+        let mut layout_of = Vec::with_capacity(boards.len());
+        let mut cells_of: HashMap<u32, Vec<(usize, usize)>> = HashMap::new();
+        for (index, board) in boards.iter().enumerate() {
+            let layout = *shapes.entry(board.shape).or_insert_with(|| {
+                layouts.push(Layout::new(board.shape, rules));
+                layouts.len() - 1
+            });
+            layout_of.push(layout);
+            for (cell, number) in board.cells.iter().enumerate() {
+                cells_of.entry(*number).or_default().push((index, cell));
+            }
+        }
+        let start = boards
+            .iter()
+            .zip(&layout_of)
+            .map(|(board, layout)| Progress::new(board, &layouts[*layout]))
             .collect();
-        let mut boards: Vec<[[u32; BINGO_SIZE]; BINGO_SIZE]> = Vec::new();
-        for board in &data.into_iter().skip(1).chunks(6) {
-            let mut v = [[0; BINGO_SIZE]; BINGO_SIZE];
-            for (i, row) in board.skip(1).enumerate() {
-                let items = row.split_whitespace().map(|s| s.parse::<u32>().unwrap());
-                for (j, col) in items.enumerate() {
-                    v[i][j] = col;
+        Index {
+            boards,
+            rules: *rules,
+            layouts,
+            layout_of,
+            cells_of,
+            start,
+        }
+    }
+}
+
+/// One game in play over an `Index`.
+#[derive(Debug, Clone)]
+struct Engine<'a> {
+    index: &'a Index<'a>,
+    progress: Vec<Progress>,
+    won: Vec<bool>,
+    turn: usize,
+}
+
+impl<'a> Engine<'a> {
+    fn new(index: &'a Index<'a>) -> Engine<'a> {
+        Engine {
+            index,
+            progress: index.start.clone(),
+            won: vec![false; index.boards.len()],
+            turn: 0,
+        }
+    }
+
+    // Marks `number` on every board still in play, returning the boards it
+    // makes win in index order.
+    fn draw(&mut self, number: u32) -> Vec<Win> {
+        self.turn += 1;
+        let mut wins = Vec::new();
+        let index = self.index;
+        let cells = match index.cells_of.get(&number) {
+            Some(cells) => cells,
+            None => return wins,
+        };
+        for &(board_index, cell) in cells {
+            if self.won[board_index] {
+                continue;
+            }
+            let board = &index.boards[board_index];
+            let layout = &index.layouts[index.layout_of[board_index]];
+            let progress = &mut self.progress[board_index];
+            let line = match progress.mark(board, layout, cell) {
+                Some(line) if progress.complete >= index.rules.lines => line,
+                _ => continue,
+            };
+            self.won[board_index] = true;
+            wins.push(Win {
+                board: board_index,
+                turn: self.turn,
+                number,
+                line,
+                score: progress.unmarked * number as u64,
+            });
+        }
+        wins
+    }
+
+    fn never_won(&self) -> Vec<usize> {
+        (0..self.won.len()).filter(|i| !self.won[*i]).collect()
+    }
+
+    fn has_won(&self, board: usize) -> bool {
+        self.won[board]
+    }
+
+    // How many more numbers the board needs to win, from the lines closest to
+    // complete, or `None` if it has too few lines left to win. With `lines:N`
+    // above 1 a cell shared by two of those lines is counted twice, which
+    // keeps this linear in the number of lines.
+    fn to_win(&self, board: usize) -> Option<usize> {
+        if self.won[board] {
+            return Some(0);
+        }
+        let layout = &self.index.layouts[self.index.layout_of[board]];
+        let progress = &self.progress[board];
+        let mut left: Vec<usize> = layout
+            .lines
+            .iter()
+            .zip(&progress.hits)
+            .map(|((_, cells), hits)| cells.len() - hits)
+            .filter(|left| *left > 0)
+            .collect();
+        let need = self.index.rules.lines.saturating_sub(progress.complete);
+        if need > left.len() {
+            return None;
+        }
+        left.sort_unstable();
+        Some(left[..need].iter().sum())
+    }
+
+    // The numbers that would each complete a line on a board still in play,
+    // with the line they complete.
+    fn one_away(&self, board: usize) -> Vec<(u32, Line)> {
+        if self.won[board] {
+            return Vec::new();
+        }
+        let layout = &self.index.layouts[self.index.layout_of[board]];
+        let progress = &self.progress[board];
+        layout
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(index, (_, cells))| progress.hits[*index] + 1 == cells.len())
+            .map(|(_, (line, cells))| {
+                let cell = cells.iter().find(|c| !progress.marked[**c]).unwrap();
+                (self.index.boards[board].cells[*cell], *line)
+            })
+            .collect()
+    }
+}
+
+// Plays every draw once through the engine. Boards winning on the same draw
+// are ranked by their index.
+fn rank_boards(boards: &[Board], draws: &[u32], rules: &Rules) -> Ranking {
+    let index = Index::new(boards, rules);
+    let mut engine = Engine::new(&index);
+    let mut wins = Vec::new();
+    for number in draws {
+        wins.extend(engine.draw(*number));
+    }
+    Ranking {
+        wins,
+        never: engine.never_won(),
+    }
+}
+
+/// How often a board came first or last over many random draw orders, and
+/// the sum of the turns it won on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Tally {
+    first: u64,
+    last: u64,
+    wins: u64,
+    turns: u64,
+    turns_squared: u64,
+}
+
+impl Tally {
+    fn merge(&mut self, other: &Tally) {
+        self.first += other.first;
+        self.last += other.last;
+        self.wins += other.wins;
+        self.turns += other.turns;
+        self.turns_squared += other.turns_squared;
+    }
+}
+
+// Plays `runs` games in parallel, each with the draw numbers in a random
+// order. Every game has its own random stream derived from `seed`, so the
+// tallies do not depend on how the games are spread over threads. Boards that
+// win on the same draw all count as first, or as last.
+fn simulate(index: &Index, numbers: &[u32], runs: u64, seed: u64) -> Vec<Tally> {
+    let boards = index.boards.len();
+    (0..runs)
+        .into_par_iter()
+        .fold(
+            || vec![Tally::default(); boards],
+            |mut tallies, run| {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                rng.set_stream(run);
+                let mut order = numbers.to_vec();
+                order.shuffle(&mut rng);
+                let mut engine = Engine::new(index);
+                let wins: Vec<Win> = order.iter().flat_map(|n| engine.draw(*n)).collect();
+                if let (Some(first), Some(last)) = (wins.first(), wins.last()) {
+                    for win in &wins {
+                        let tally = &mut tallies[win.board];
+                        let turn = win.turn as u64;
+                        tally.wins += 1;
+                        tally.turns += turn;
+                        tally.turns_squared += turn * turn;
+                        tally.first += (win.turn == first.turn) as u64;
+                        tally.last += (win.turn == last.turn) as u64;
+                    }
                 }
+                tallies
+            },
+        )
+        .reduce(
+            || vec![Tally::default(); boards],
+            |mut a, b| {
+                a.iter_mut().zip(&b).for_each(|(a, b)| a.merge(b));
+                a
+            },
+        )
+}
+
+const Z_95: f64 = 1.96;
+
+// The Wilson score interval for a proportion, which stays inside [0, 1] even
+// for boards that almost never or almost always win.
+fn wilson(successes: u64, trials: u64) -> (f64, f64, f64) {
+    let (k, n) = (successes as f64, trials as f64);
+    let p = k / n;
+    let z2 = Z_95 * Z_95;
+    let centre = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let spread = Z_95 * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / (1.0 + z2 / n);
+    (p, (centre - spread).max(0.0), (centre + spread).min(1.0))
+}
+
+fn print_simulation(tallies: &[Tally], runs: u64, seed: u64) {
+    println!("{} random draw orders, seed {}, 95% intervals:", runs, seed);
+    println!(
+        "{:>6}  {:<24}  {:<24}  Winning turn",
+        "Board", "P(first)", "P(last)"
+    );
+    for (board, tally) in tallies.iter().enumerate() {
+        let probability = |count| {
+            let (p, low, high) = wilson(count, runs);
+            format!("{:.4} [{:.4}, {:.4}]", p, low, high)
+        };
+        let turn = match tally.wins {
+            0 => "never wins".to_string(),
+            wins => {
+                let n = wins as f64;
+                let mean = tally.turns as f64 / n;
+                let variance = if wins > 1 {
+                    (tally.turns_squared as f64 - n * mean * mean) / (n - 1.0)
+                } else {
+                    0.0
+                };
+                let half = Z_95 * (variance.max(0.0) / n).sqrt();
+                let share = if wins < runs {
+                    format!(", wins in {:.2}% of games", 100.0 * n / runs as f64)
+                } else {
+                    String::new()
+                };
+                format!("{:.2} ± {:.2}{}", mean, half, share)
             }
-            boards.push(v);
+        };
+        println!(
+            "{:>6}  {:<24}  {:<24}  {}",
+            board + 1,
+            probability(tally.first),
+            probability(tally.last),
+            turn
+        );
+    }
+}
+
+fn print_rigged(index: &Index, draws: &[u32], board: usize, goal: Goal) {
+    let wanted = match goal {
+        Goal::First => "first",
+        Goal::Last => "last",
+    };
+    let rigged = match board
+        .checked_sub(1)
+        .ok_or_else(|| "boards are numbered from 1".to_string())
+        .and_then(|board| rig::rig(index, draws, board, goal))
+    {
+        Ok(rigged) => rigged,
+        Err(e) => {
+            println!("Cannot rig board {} to win {}: {}", board, wanted, e);
+            return;
+        }
+    };
+    println!(
+        "Rigged draw for board {} to win {} ({} numbers{}):",
+        board,
+        wanted,
+        rigged.draws.len(),
+        if rigged.shortest {
+            ", shortest"
+        } else {
+            ", may not be shortest"
         }
-        let (_, part_1) = find_winning_board(&boards, &results);
-        // Part 2
-        for _ in 1..boards.len() {
-            let (win_board, _) = find_winning_board(&boards, &results);
-            boards.remove(win_board);
+    );
+    let numbers: Vec<String> = rigged.draws.iter().map(u32::to_string).collect();
+    println!("{}", numbers.join(","));
+    match rig::verify(index, &rigged.draws, board - 1, goal) {
+        Ok(win) => println!(
+            "Verified: board {} wins {} on turn {} with {}, score {}",
+            board, wanted, win.turn, win.line, win.score
+        ),
+        Err(e) => println!("Verification failed: {}", e),
+    }
+}
+
+fn print_ranking(ranking: &Ranking) {
+    println!(
+        "{:>5}  {:>6}  {:>5}  {:>6}  {:<10}  {:>8}",
+        "Rank", "Board", "Turn", "Number", "Line", "Score"
+    );
+    for (rank, win) in ranking.wins.iter().enumerate() {
+        println!(
+            "{:>5}  {:>6}  {:>5}  {:>6}  {:<10}  {:>8}",
+            rank + 1,
+            win.board + 1,
+            win.turn,
+            win.number,
+            win.line.to_string(),
+            win.score
+        );
+    }
+    if !ranking.never.is_empty() {
+        let never: Vec<String> = ranking.never.iter().map(|b| (b + 1).to_string()).collect();
+        println!("Never win: {}", never.join(", "));
+    }
+}
+
+fn print_part(part: usize, win: Option<&Win>) {
+    match win {
+        Some(win) => println!("Part {}: {:?}", part, win.score),
+        None => println!("Part {}: no board wins", part),
+    }
+}
+
+fn main() {
+    let cli = Cli::from_args();
+    let config = Config::for_day(4);
+    let perf = cli.perf.recorder(&config);
+    let size = cli.size;
+    let input = config.input(cli.input);
+    let rules = Rules::new(&cli.rule);
+    if cli.live {
+        let boards = read_lines(input, &cli.normalize).and_then(|l| parse_live_boards(l, size));
+        let result = boards.and_then(|boards| {
+            let index = Index::new(&boards, &rules);
+            live::run(&index, io::stdin().lock())
+        });
+        if let Err(e) = result {
+            eprintln!("{}", e);
+        }
+        return;
+    }
+    match read_lines(input, &cli.normalize).and_then(|l| parse_game(l, size)) {
+        Ok(Game { draws, boards }) => {
+            let ranking = perf.part(1, || rank_boards(&boards, &draws, &rules));
+            if cli.ranking {
+                print_ranking(&ranking);
+            }
+            print_part(1, ranking.wins.first());
+            print_part(2, ranking.wins.last());
+            if let Some(runs) = cli.simulate {
+                let index = Index::new(&boards, &rules);
+                let tallies = simulate(&index, &draws, runs, cli.seed);
+                print_simulation(&tallies, runs, cli.seed);
+            }
+            if let Some(board) = cli.rig {
+                let index = Index::new(&boards, &rules);
+                let goal = if cli.last { Goal::Last } else { Goal::First };
+                print_rigged(&index, &draws, board, goal);
+            }
         }
-        let (_, part_2) = find_winning_board(&boards, &results);
-        println!("Part 1: {:?}", part_1);
-        println!("Part 2: {:?}", part_2);
+        Err(e) => eprintln!("{}", e),
     }
 }
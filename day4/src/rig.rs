@@ -0,0 +1,399 @@
+use crate::{rank_boards, Index, Win};
+use std::collections::HashMap;
+
+// Rigging looks for the fewest draw numbers, each drawn at most once, that
+// make one board finish first or last. Marks only ever accumulate, so which
+// boards have won depends only on the set of numbers drawn so far, and the
+// search works on sets:
+//
+// - to win first, the board needs a set of lines completed while no other
+//   board has any completed;
+// - to win last, every other board has to win from a set that still leaves
+//   the board one number short, and that number is drawn at the end.
+//
+// Each board's ways to win are the smallest sets of numbers that complete
+// enough lines, and a branch and bound search picks one for every board that
+// has to win. Finishing last is a covering problem, so the search gives up
+// after `BUDGET` steps and keeps the best order it has found.
+
+const BUDGET: usize = 200_000;
+
+// Listing the ways to win takes a step for each combination of lines and for
+// each comparison between two ways, and gives up after this many.
+const LISTING_BUDGET: usize = 20_000_000;
+
+/// Whether the rigged board should be the first or the last to win.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Goal {
+    First,
+    Last,
+}
+
+/// A draw order found by `rig`. `shortest` is false when the search ran out
+/// of steps before it could rule out anything shorter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Rigged {
+    pub(crate) draws: Vec<u32>,
+    pub(crate) shortest: bool,
+}
+
+// Every way for each board to win, as sets of indices into the distinct draw
+// numbers. Ways that need a number that is never drawn are left out, and so
+// are ways that contain another way. `None` if that takes more than
+// `LISTING_BUDGET` steps.
+fn ways_to_win(index: &Index, ids: &HashMap<u32, usize>) -> Option<Vec<Vec<Vec<usize>>>> {
+    let need = index.rules.lines;
+    let mut steps = 0;
+    let mut all = Vec::with_capacity(index.boards.len());
+    for (board_index, board) in index.boards.iter().enumerate() {
+        let layout = &index.layouts[index.layout_of[board_index]];
+        let mut ways: Vec<Vec<usize>> = Vec::new();
+        let mut chosen = Vec::new();
+        let listed = combinations(layout.lines.len(), need, 0, &mut chosen, &mut |lines| {
+            steps += 1;
+            if steps > LISTING_BUDGET {
+                return false;
+            }
+            let mut way = Vec::new();
+            for line in lines {
+                for cell in &layout.lines[*line].1 {
+                    if Some(*cell) == layout.free {
+                        continue;
+                    }
+                    match ids.get(&board.cells[*cell]) {
+                        Some(id) => way.push(*id),
+                        None => return true,
+                    }
+                }
+            }
+            way.sort_unstable();
+            way.dedup();
+            ways.push(way);
+            true
+        });
+        if !listed {
+            return None;
+        }
+        ways.sort_by_key(Vec::len);
+        ways.dedup();
+        let mut minimal: Vec<Vec<usize>> = Vec::new();
+        for way in ways {
+            steps += minimal.len();
+            if steps > LISTING_BUDGET {
+                return None;
+            }
+            if !minimal.iter().any(|smaller| is_subset(smaller, &way)) {
+                minimal.push(way);
+            }
+        }
+        all.push(minimal);
+    }
+    Some(all)
+}
+
+// Calls `visit` with every way of choosing `k` of `0..n`, in order, until it
+// returns false. Returns false if it was stopped.
+fn combinations(
+    n: usize,
+    k: usize,
+    start: usize,
+    chosen: &mut Vec<usize>,
+    visit: &mut dyn FnMut(&[usize]) -> bool,
+) -> bool {
+    if chosen.len() == k {
+        return visit(chosen);
+    }
+    for next in start..n {
+        chosen.push(next);
+        let going = combinations(n, k, next + 1, chosen, visit);
+        chosen.pop();
+        if !going {
+            return false;
+        }
+    }
+    true
+}
+
+// Both slices are sorted.
+fn is_subset(small: &[usize], large: &[usize]) -> bool {
+    let mut large = large.iter();
+    small.iter().all(|n| large.any(|m| m == n))
+}
+
+struct Search<'a> {
+    ways: &'a [Vec<Vec<usize>>],
+    must_win: Vec<usize>,
+    must_not_win: Vec<usize>,
+    banned: Option<usize>,
+    drawn: Vec<bool>,
+    count: usize,
+    best: Option<Vec<usize>>,
+    steps: usize,
+}
+
+impl<'a> Search<'a> {
+    fn new(ways: &'a [Vec<Vec<usize>>], numbers: usize) -> Search<'a> {
+        Search {
+            ways,
+            must_win: Vec::new(),
+            must_not_win: Vec::new(),
+            banned: None,
+            drawn: vec![false; numbers],
+            count: 0,
+            best: None,
+            steps: 0,
+        }
+    }
+
+    fn has_won(&self, board: usize) -> bool {
+        self.ways[board]
+            .iter()
+            .any(|way| way.iter().all(|n| self.drawn[*n]))
+    }
+
+    // How many more numbers a way needs, unless it needs the banned one.
+    fn cost(&self, way: &[usize]) -> Option<usize> {
+        if self.banned.is_some_and(|banned| way.contains(&banned)) {
+            return None;
+        }
+        Some(way.iter().filter(|n| !self.drawn[**n]).count())
+    }
+
+    fn draw(&mut self, way: &[usize]) -> Vec<usize> {
+        let added: Vec<usize> = way.iter().copied().filter(|n| !self.drawn[*n]).collect();
+        for n in &added {
+            self.drawn[*n] = true;
+        }
+        self.count += added.len();
+        added
+    }
+
+    fn undraw(&mut self, added: &[usize]) {
+        for n in added {
+            self.drawn[*n] = false;
+        }
+        self.count -= added.len();
+    }
+
+    // Branches on the board that is furthest from winning, whose cheapest way
+    // is also a lower bound on what is left to draw.
+    fn search(&mut self) {
+        self.steps += 1;
+        if self.steps > BUDGET || self.must_not_win.iter().any(|b| self.has_won(*b)) {
+            return;
+        }
+        let mut pick = None;
+        for board in &self.must_win {
+            if self.has_won(*board) {
+                continue;
+            }
+            let cheapest = match self.ways[*board].iter().filter_map(|w| self.cost(w)).min() {
+                Some(cost) => cost,
+                None => return,
+            };
+            if pick.is_none_or(|(_, bound)| cheapest > bound) {
+                pick = Some((*board, cheapest));
+            }
+        }
+        let best = self.best.as_ref().map_or(usize::MAX, Vec::len);
+        let (board, bound) = match pick {
+            Some(pick) => pick,
+            None => {
+                if self.count < best {
+                    self.best = Some((0..self.drawn.len()).filter(|n| self.drawn[*n]).collect());
+                }
+                return;
+            }
+        };
+        if self.count + bound >= best {
+            return;
+        }
+        let mut ways: Vec<(usize, &'a Vec<usize>)> = self.ways[board]
+            .iter()
+            .filter_map(|way| self.cost(way).map(|cost| (cost, way)))
+            .collect();
+        ways.sort_by_key(|(cost, _)| *cost);
+        for (_, way) in ways {
+            let added = self.draw(way);
+            self.search();
+            self.undraw(&added);
+        }
+    }
+}
+
+fn list(boards: &[usize]) -> String {
+    let boards: Vec<String> = boards.iter().map(|b| (b + 1).to_string()).collect();
+    boards.join(", ")
+}
+
+// Every board that any of `board`'s ways to win would also complete, or
+// `None` if one of them completes no other board.
+fn always_completed(ways: &[Vec<Vec<usize>>], board: usize) -> Option<Vec<usize>> {
+    let mut common: Option<Vec<usize>> = None;
+    for way in &ways[board] {
+        let completed: Vec<usize> = (0..ways.len())
+            .filter(|other| *other != board)
+            .filter(|other| ways[*other].iter().any(|w| is_subset(w, way)))
+            .collect();
+        if completed.is_empty() {
+            return None;
+        }
+        common = Some(match common {
+            Some(common) => common
+                .into_iter()
+                .filter(|b| completed.contains(b))
+                .collect(),
+            None => completed,
+        });
+    }
+    common
+}
+
+// Finds the shortest draw order, using each distinct number in `numbers` at
+// most once, that makes `board` win first or last. The error says why there
+// is none.
+pub(crate) fn rig(
+    index: &Index,
+    numbers: &[u32],
+    board: usize,
+    goal: Goal,
+) -> Result<Rigged, String> {
+    let boards = index.boards.len();
+    if board >= boards {
+        return Err(format!(
+            "there is no board {}, the input has {}",
+            board + 1,
+            boards
+        ));
+    }
+    let mut distinct = Vec::new();
+    let mut ids = HashMap::new();
+    for n in numbers {
+        ids.entry(*n).or_insert_with(|| {
+            distinct.push(*n);
+            distinct.len() - 1
+        });
+    }
+    let ways = ways_to_win(index, &ids).ok_or_else(|| {
+        format!(
+            "gave up after {} steps listing the ways to win, there are too many \
+             combinations of {} lines",
+            LISTING_BUDGET, index.rules.lines
+        )
+    })?;
+    if ways[board].is_empty() {
+        return Err(format!(
+            "board {} can never win, every way needs a number that is never drawn",
+            board + 1
+        ));
+    }
+    let others: Vec<usize> = (0..boards).filter(|b| *b != board).collect();
+    let mut search = Search::new(&ways, distinct.len());
+    let last = match goal {
+        Goal::First => {
+            search.must_win = vec![board];
+            search.must_not_win = others;
+            search.search();
+            None
+        }
+        Goal::Last => {
+            if let Some(never) = others.iter().find(|b| ways[**b].is_empty()) {
+                return Err(format!(
+                    "board {} can never win, so board {} cannot be last",
+                    never + 1,
+                    board + 1
+                ));
+            }
+            let blocked = others.iter().find(|b| {
+                ways[**b]
+                    .iter()
+                    .all(|way| ways[board].iter().any(|w| is_subset(w, way)))
+            });
+            if let Some(blocked) = blocked {
+                return Err(format!(
+                    "board {} cannot win without board {} winning too",
+                    blocked + 1,
+                    board + 1
+                ));
+            }
+            search.must_win = others;
+            search.must_not_win = vec![board];
+            let mut last = None;
+            for way in &ways[board] {
+                for (i, final_number) in way.iter().enumerate() {
+                    let before = search.best.as_ref().map(Vec::len);
+                    let rest: Vec<usize> = [&way[..i], &way[i + 1..]].concat();
+                    search.banned = Some(*final_number);
+                    let added = search.draw(&rest);
+                    search.search();
+                    search.undraw(&added);
+                    if search.best.as_ref().map(Vec::len) != before {
+                        last = Some(*final_number);
+                    }
+                }
+            }
+            last
+        }
+    };
+    let shortest = search.steps <= BUDGET;
+    let chosen = match search.best {
+        Some(chosen) => chosen,
+        None if !shortest => {
+            return Err(format!(
+                "gave up after {} steps without finding a draw order",
+                BUDGET
+            ))
+        }
+        None => {
+            return Err(match goal {
+                Goal::First => match always_completed(&ways, board) {
+                    Some(common) if !common.is_empty() => format!(
+                        "every way for board {} to win also completes board {}",
+                        board + 1,
+                        list(&common)
+                    ),
+                    _ => format!(
+                        "every way for board {} to win also completes another board",
+                        board + 1
+                    ),
+                },
+                Goal::Last => format!(
+                    "no order of the drawn numbers lets every other board win before board {}",
+                    board + 1
+                ),
+            })
+        }
+    };
+    let mut draws: Vec<u32> = chosen.iter().map(|id| distinct[*id]).collect();
+    draws.extend(last.map(|id| distinct[id]));
+    Ok(Rigged { draws, shortest })
+}
+
+// Plays a rigged order through the normal game and checks that the board
+// finishes where it should, on a draw of its own.
+pub(crate) fn verify(
+    index: &Index,
+    draws: &[u32],
+    board: usize,
+    goal: Goal,
+) -> Result<Win, String> {
+    let ranking = rank_boards(index.boards, draws, &index.rules);
+    let wins = &ranking.wins;
+    let (win, neighbour) = match goal {
+        Goal::First => (wins.first(), wins.get(1)),
+        Goal::Last => (wins.last(), wins.len().checked_sub(2).map(|i| &wins[i])),
+    };
+    match win {
+        Some(win) if win.board != board => Err(format!("board {} won instead", win.board + 1)),
+        Some(win) if neighbour.is_some_and(|n| n.turn == win.turn) => Err(format!(
+            "board {} tied with board {}",
+            board + 1,
+            neighbour.unwrap().board + 1
+        )),
+        Some(_) if goal == Goal::Last && !ranking.never.is_empty() => {
+            Err(format!("boards {} never won", list(&ranking.never)))
+        }
+        Some(win) => Ok(*win),
+        None => Err("no board won".to_string()),
+    }
+}
@@ -1,25 +1,37 @@
+use aoc::config::Config;
+use aoc::input::{Blanks, Lines, Normalize, NormalizeOpts};
+use aoc::perf::PerfOpts;
 use std::cmp;
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io;
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Day5", about = "Hydrothermal Venture")]
 struct Cli {
-    /// Input file
+    /// Input file, defaults to `input` in aoc.toml
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    normalize: NormalizeOpts,
+
+    #[structopt(flatten)]
+    perf: PerfOpts,
 }
 
 // The output is wrapped in a Result to allow matching on errors
-// Returns an Iterator to the Reader of the lines of the file.
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+// Returns an Iterator to the normalised lines of the file.
+fn read_lines<P>(
+    filename: P,
+    normalize: &NormalizeOpts,
+) -> io::Result<Normalize<Lines<io::BufReader<File>>>>
 where
     P: AsRef<Path>,
 {
     let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    Ok(normalize.read(io::BufReader::new(file), Blanks::Drop))
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -28,12 +40,6 @@ struct Point {
     y: i32,
 }
 
-impl Point {
-    fn max_value(&self) -> i32 {
-        cmp::max(self.x, self.y)
-    }
-}
-
 impl From<&str> for Point {
     fn from(s: &str) -> Self {
         let p_strings: Vec<&str> = s.split(",").collect();
@@ -58,10 +64,6 @@ impl Pair {
     fn match_y(&self) -> bool {
         self.start.y == self.end.y
     }
-
-    fn max_value(&self) -> i32 {
-        cmp::max(self.start.max_value(), self.end.max_value())
-    }
 }
 
 impl From<String> for Pair {
@@ -74,72 +76,139 @@ impl From<String> for Pair {
     }
 }
 
-fn unit(i: i32) -> i32 {
-    i / i.abs()
+/// A segment as the sweep sees it: the columns it covers, and the rows it
+/// covers in its first column, which shift by `slope` each column after.
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    start: i64,
+    end: i64,
+    low: i64,
+    high: i64,
+    slope: i64,
+}
+
+impl From<&Pair> for Span {
+    fn from(pair: &Pair) -> Self {
+        let (s, e) = if pair.start.x > pair.end.x {
+            (pair.end, pair.start)
+        } else {
+            (pair.start, pair.end)
+        };
+        let (low, high, slope) = if pair.match_x() {
+            (cmp::min(s.y, e.y) as i64, cmp::max(s.y, e.y) as i64, 0)
+        } else {
+            (s.y as i64, s.y as i64, (e.y as i64 - s.y as i64).signum())
+        };
+        Span {
+            start: s.x as i64,
+            end: e.x as i64,
+            low,
+            high,
+            slope,
+        }
+    }
+}
+
+// Counts the rows of column `x` that at least two spans cover. `edges` is
+// scratch space kept between columns.
+fn overlaps_in_column(x: i64, active: &[Span], edges: &mut Vec<(i64, i32)>) -> u64 {
+    edges.clear();
+    for span in active {
+        let shift = span.slope * (x - span.start);
+        edges.push((span.low + shift, 1));
+        edges.push((span.high + shift + 1, -1));
+    }
+    edges.sort_unstable();
+    let mut covering = 0;
+    let mut overlaps = 0;
+    let mut from = 0;
+    for &(y, change) in edges.iter() {
+        if covering > 1 {
+            overlaps += (y - from) as u64;
+        }
+        covering += change;
+        from = y;
+    }
+    overlaps
 }
 
-fn create_field_map(size: usize, data: &Vec<Pair>) -> Vec<u32> {
-    let mut field = vec![0u32; size * size];
-    let i_size = size as i32;
-    for pair in data {
-        if pair.match_x() {
-            let (s, e) = if pair.start.y > pair.end.y {
-                (pair.end, pair.start)
-            } else {
-                (pair.start, pair.end)
-            };
-
-            for y in s.y..(e.y + 1) {
-                field[(pair.start.x * i_size + y) as usize] += 1;
+// The first column after `x` where `overlaps_in_column` may count something
+// else: where a segment starts or ends, or where two segments with different
+// slopes meet. Segments with the same slope keep the same overlap.
+fn next_change(x: i64, active: &[Span], next_start: Option<i64>) -> i64 {
+    let row = |span: &Span| span.low + span.slope * (x - span.start);
+    let mut next = active
+        .iter()
+        .map(|span| span.end + 1)
+        .chain(next_start)
+        .min()
+        .unwrap_or(x + 1);
+    for (i, a) in active.iter().enumerate() {
+        if a.low != a.high {
+            return x + 1;
+        }
+        for b in &active[..i] {
+            let closing = b.slope - a.slope;
+            if closing == 0 {
+                continue;
             }
-        } else if pair.match_y() {
-            let (s, e) = if pair.start.x > pair.end.x {
-                (pair.end, pair.start)
-            } else {
-                (pair.start, pair.end)
-            };
-
-            for x in s.x..(e.x + 1) {
-                field[(x * i_size + pair.start.y) as usize] += 1;
+            let gap = row(a) - row(b);
+            if gap == 0 {
+                return x + 1;
             }
-        } else {
-            let (s, e) = if pair.start.x > pair.end.x {
-                (pair.start, pair.end)
-            } else {
-                (pair.end, pair.start)
-            };
-            let slop = unit(e.y - s.y);
-
-            for step in 0..((s.x - e.x) + 1) {
-                field[((s.x + (step * -1)) * i_size + (s.y + (step * slop))) as usize] += 1;
+            if gap % closing == 0 && gap / closing > 0 {
+                next = cmp::min(next, x + gap / closing);
             }
         }
     }
-    field
+    next
+}
+
+// Sweeps across the columns the segments cover, holding only the segments
+// that cross the current column, so memory grows with the number of segments
+// rather than the size of the field. Between changes every column has the
+// same overlap, so each run of them is counted in one go.
+fn count_overlaps(pairs: &[Pair]) -> u64 {
+    let mut spans: Vec<Span> = pairs.iter().map(Span::from).collect();
+    spans.sort_by_key(|span| span.start);
+    let mut spans = spans.into_iter().peekable();
+    let mut active: Vec<Span> = Vec::new();
+    let mut edges = Vec::new();
+    let mut overlaps = 0;
+    let mut x = i64::MIN;
+    loop {
+        active.retain(|span| span.end >= x);
+        if active.is_empty() {
+            match spans.peek() {
+                Some(span) => x = cmp::max(x, span.start),
+                None => return overlaps,
+            }
+        }
+        while let Some(span) = spans.next_if(|span| span.start == x) {
+            active.push(span);
+        }
+        let count = overlaps_in_column(x, &active, &mut edges);
+        let next = next_change(x, &active, spans.peek().map(|span| span.start));
+        overlaps += count * (next - x) as u64;
+        x = next;
+    }
 }
 
 fn main() {
     let cli = Cli::from_args();
-    let file_lines = read_lines(cli.input);
+    let config = Config::for_day(5);
+    let perf = cli.perf.recorder(&config);
+    let input = config.input(cli.input);
+    let file_lines = read_lines(input, &cli.normalize);
     if let Ok(lines) = file_lines {
-        let part_2_data: Vec<_> = lines
-            .filter_map(Result::ok)
-            .map(|i| Pair::from(i))
-            .collect();
+        let part_2_data: Vec<_> = lines.filter_map(Result::ok).map(Pair::from).collect();
         let part_1_data: Vec<_> = part_2_data
             .iter()
             .cloned()
             .filter(|p| p.match_x() || p.match_y())
             .collect();
-        let size: usize = (part_2_data.iter().map(|p| p.max_value()).max().unwrap() + 1) as usize;
-        let part_1 = create_field_map(size, &part_1_data)
-            .into_iter()
-            .filter(|i| *i > 1)
-            .count();
-        let part_2 = create_field_map(size, &part_2_data)
-            .into_iter()
-            .filter(|i| *i > 1)
-            .count();
+        let part_1 = perf.part(1, || count_overlaps(&part_1_data));
+        let part_2 = perf.part(2, || count_overlaps(&part_2_data));
         println!("Part 1: {:?}", part_1);
         println!("Part 2: {:?}", part_2);
     }
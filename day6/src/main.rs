@@ -1,29 +1,60 @@
+use aoc::config::Config;
+use aoc::input::{Blanks, Lines, Normalize, NormalizeOpts};
+use aoc::perf::PerfOpts;
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io;
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Day6", about = "Lanternfish")]
 struct Cli {
-    /// Input file
+    /// Input file, defaults to `input` in aoc.toml
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
+
+    /// Days to simulate for part 1 [default: 80]
+    #[structopt(long)]
+    part_1_days: Option<usize>,
+
+    /// Days to simulate for part 2 [default: 256]
+    #[structopt(long)]
+    part_2_days: Option<usize>,
+
+    #[structopt(flatten)]
+    normalize: NormalizeOpts,
+
+    #[structopt(flatten)]
+    perf: PerfOpts,
 }
 
 // The output is wrapped in a Result to allow matching on errors
-// Returns an Iterator to the Reader of the lines of the file.
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+// Returns an Iterator to the normalised lines of the file.
+fn read_lines<P>(
+    filename: P,
+    normalize: &NormalizeOpts,
+) -> io::Result<Normalize<Lines<io::BufReader<File>>>>
 where
     P: AsRef<Path>,
 {
     let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    Ok(normalize.read(io::BufReader::new(file), Blanks::Drop))
 }
 
 fn main() {
     let cli = Cli::from_args();
-    let file_lines = read_lines(cli.input);
+    let config = Config::for_day(6);
+    let perf = cli.perf.recorder(&config);
+    let input = config.input(cli.input);
+    let part_1_days = cli
+        .part_1_days
+        .or_else(|| config.get("part_1_days"))
+        .unwrap_or(80);
+    let part_2_days = cli
+        .part_2_days
+        .or_else(|| config.get("part_2_days"))
+        .unwrap_or(256);
+    let file_lines = read_lines(input, &cli.normalize);
     if let Ok(lines) = file_lines {
         let fishes: Vec<usize> = lines
             .filter_map(Result::ok)
@@ -36,17 +67,17 @@ fn main() {
         for fish in fishes {
             gens[fish] += 1;
         }
-        let mut part_1 = 0u64;
-        for i in 0..256 {
-            if i == 80 {
-                part_1 = gens.iter().sum()
+        let simulate = |mut gens: [u64; 9], days: usize| {
+            for _ in 0..days {
+                // let [a, b, c, d, e, f, g, h, i] = gens;
+                // gens = [b, c, d, e, f, g, h + a, i, a];
+                gens.rotate_left(1);
+                gens[6] += gens[8];
             }
-            // let [a, b, c, d, e, f, g, h, i] = gens;
-            // gens = [b, c, d, e, f, g, h + a, i, a];
-            gens.rotate_left(1);
-            gens[6] += gens[8];
-        }
-        let part_2: u64 = gens.iter().sum();
+            gens.iter().sum::<u64>()
+        };
+        let part_1 = perf.part(1, || simulate(gens, part_1_days));
+        let part_2 = perf.part(2, || simulate(gens, part_2_days));
         println!("Part 1: {}", part_1);
         println!("Part 2: {}", part_2);
     }
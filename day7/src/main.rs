@@ -1,14 +1,23 @@
+use aoc::config::Config;
+use aoc::input::{Blanks, Lines, Normalize, NormalizeOpts};
+use aoc::perf::PerfOpts;
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io;
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Day7", about = "The Treachery of Whales")]
 struct Cli {
-    /// Input file
+    /// Input file, defaults to `input` in aoc.toml
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    normalize: NormalizeOpts,
+
+    #[structopt(flatten)]
+    perf: PerfOpts,
 }
 
 trait AbsoluteDifference {
@@ -26,13 +35,16 @@ impl AbsoluteDifference for u32 {
 }
 
 // The output is wrapped in a Result to allow matching on errors
-// Returns an Iterator to the Reader of the lines of the file.
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+// Returns an Iterator to the normalised lines of the file.
+fn read_lines<P>(
+    filename: P,
+    normalize: &NormalizeOpts,
+) -> io::Result<Normalize<Lines<io::BufReader<File>>>>
 where
     P: AsRef<Path>,
 {
     let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    Ok(normalize.read(io::BufReader::new(file), Blanks::Drop))
 }
 
 fn calc_fuel_cost_p1(xs: &Vec<u32>, pos: u32) -> u32 {
@@ -45,7 +57,10 @@ fn calc_fuel_cost_p2(xs: &Vec<u32>, pos: u32) -> u32 {
 
 fn main() {
     let cli = Cli::from_args();
-    if let Ok(lines) = read_lines(&cli.input) {
+    let config = Config::for_day(7);
+    let perf = cli.perf.recorder(&config);
+    let input = config.input(cli.input);
+    if let Ok(lines) = read_lines(&input, &cli.normalize) {
         let data: Vec<u32> = lines
             .filter_map(Result::ok)
             .next()
@@ -53,14 +68,18 @@ fn main() {
             .split(",")
             .map(|s| s.parse().unwrap())
             .collect();
-        let part_1: u32 = (0..*data.iter().max().unwrap())
-            .map(|i| calc_fuel_cost_p1(&data, i))
-            .min()
-            .unwrap();
-        let part_2: u32 = (0..*data.iter().max().unwrap())
-            .map(|i| calc_fuel_cost_p2(&data, i))
-            .min()
-            .unwrap();
+        let part_1: u32 = perf.part(1, || {
+            (0..*data.iter().max().unwrap())
+                .map(|i| calc_fuel_cost_p1(&data, i))
+                .min()
+                .unwrap()
+        });
+        let part_2: u32 = perf.part(2, || {
+            (0..*data.iter().max().unwrap())
+                .map(|i| calc_fuel_cost_p2(&data, i))
+                .min()
+                .unwrap()
+        });
         println!("Part 1: {}", part_1);
         println!("Part 2: {}", part_2);
     }
@@ -1,6 +1,9 @@
+use aoc::config::Config;
+use aoc::input::{Blanks, Lines, Normalize, NormalizeOpts};
+use aoc::perf::PerfOpts;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
-use std::io::{self, BufRead};
+use std::io;
 use std::iter::FromIterator;
 use std::path::{Path, PathBuf};
 use structopt::StructOpt;
@@ -8,9 +11,15 @@ use structopt::StructOpt;
 #[derive(Debug, StructOpt)]
 #[structopt(name = "Day8", about = "Seven Segment Search")]
 struct Cli {
-    /// Input file
+    /// Input file, defaults to `input` in aoc.toml
     #[structopt(parse(from_os_str))]
-    input: PathBuf,
+    input: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    normalize: NormalizeOpts,
+
+    #[structopt(flatten)]
+    perf: PerfOpts,
 }
 
 const TWO_DECODE: [usize; 2] = [2, 5];
@@ -107,18 +116,24 @@ impl SegmentData {
 }
 
 // The output is wrapped in a Result to allow matching on errors
-// Returns an Iterator to the Reader of the lines of the file.
-fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
+// Returns an Iterator to the normalised lines of the file.
+fn read_lines<P>(
+    filename: P,
+    normalize: &NormalizeOpts,
+) -> io::Result<Normalize<Lines<io::BufReader<File>>>>
 where
     P: AsRef<Path>,
 {
     let file = File::open(filename)?;
-    Ok(io::BufReader::new(file).lines())
+    Ok(normalize.read(io::BufReader::new(file), Blanks::Drop))
 }
 
 fn main() {
     let cli = Cli::from_args();
-    let file_lines = read_lines(cli.input);
+    let config = Config::for_day(8);
+    let perf = cli.perf.recorder(&config);
+    let input = config.input(cli.input);
+    let file_lines = read_lines(input, &cli.normalize);
     if let Ok(lines) = file_lines {
         let data: Vec<SegmentData> = lines
             .filter_map(Result::ok)
@@ -134,14 +149,16 @@ fn main() {
             })
             .collect();
         let part_1_filter = BTreeSet::from_iter([2, 4, 3, 7].iter());
-        let part_1 = data
-            .iter()
-            .flat_map(|s| s.output.iter().filter(|i| part_1_filter.contains(&i.len())))
-            .count();
-        let part_2: u32 = data
-            .iter()
-            .map(|sd| sd.get_output().parse::<u32>().unwrap())
-            .sum();
+        let part_1 = perf.part(1, || {
+            data.iter()
+                .flat_map(|s| s.output.iter().filter(|i| part_1_filter.contains(&i.len())))
+                .count()
+        });
+        let part_2: u32 = perf.part(2, || {
+            data.iter()
+                .map(|sd| sd.get_output().parse::<u32>().unwrap())
+                .sum()
+        });
 
         println!("Part 1: {:?}", part_1);
         println!("Part 2: {:?}", part_2);